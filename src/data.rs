@@ -1,10 +1,17 @@
 mod parser;
 mod types;
+#[cfg(test)]
+mod validate;
 
 pub use parser::{
-    parse_dialogs, parse_enemies, parse_items, parse_maps, parse_npcs, parse_quests, parse_shops,
+    MAX_SKILLS, ParseError, parse_bosses, parse_dialogs, parse_enemies, parse_items, parse_levels,
+    parse_loot, parse_maps, parse_npcs, parse_quests, parse_shops, parse_skills, try_parse_bosses,
+    try_parse_dialogs, try_parse_enemies, try_parse_items, try_parse_levels, try_parse_loot,
+    try_parse_maps, try_parse_npcs, try_parse_quests, try_parse_shops, try_parse_skills,
 };
 pub use types::{
-    Dialog, DialogAction, DialogCondition, DialogLine, Enemy, Item, ItemKind, Map, Npc, NpcType,
-    PlayerStats, Quest, QuestProgress, QuestType, Shop, Tile,
+    Boss, BossPattern, BossPhase, Dialog, DialogAction, DialogCondition, DialogLine, Enemy,
+    EnemyAi, EquipmentStats, Item, ItemKind, LevelGrowth, LevelUp, LootSource, LootTable, Map,
+    MapLink, MapTrigger, Npc, NpcType, PlayerStats, Quest, QuestProgress, QuestType, Shop, Skill,
+    SkillEffect, StatusEffect, StatusKind, Tile, TriggerAction,
 };
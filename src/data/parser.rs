@@ -1,103 +1,358 @@
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::str::FromStr;
 
 use super::types::{
-    Dialog, DialogAction, DialogCondition, DialogLine, Enemy, Item, ItemKind, Map, Npc, NpcType,
-    Quest, QuestType, Shop, Tile,
+    Boss, BossPattern, BossPhase, Dialog, DialogAction, DialogCondition, DialogLine, Enemy,
+    EnemyAi, Item, ItemKind, LevelGrowth, LootSource, LootTable, Map, MapLink, MapTrigger, Npc,
+    NpcType, Quest, QuestType, Shop, Skill, SkillEffect, StatusEffect, StatusKind, Tile,
+    TriggerAction,
 };
 
+/// 데이터 파일 파싱 오류 (line은 1부터 시작)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub reason: String,
+}
+
+/// 스킬은 4~9번 키에 하나씩 배정되므로 이보다 많이 둘 수 없다
+pub const MAX_SKILLS: usize = 6;
+
+/// items.dat에 최대 겹침 수가 없는 소비/퀘스트 아이템의 기본값
+const DEFAULT_MAX_STACK: u32 = 99;
+
 pub fn parse_items(data: &str) -> Vec<Item> {
-    let mut items = Vec::new();
+    read_items(data).0
+}
 
-    for line in data.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
+pub fn try_parse_items(data: &str) -> Result<Vec<Item>, Vec<ParseError>> {
+    into_result(read_items(data))
+}
 
-        let parts: Vec<&str> = line.split(':').collect();
-        if parts.len() < 5 {
-            continue;
-        }
+fn read_items(data: &str) -> (Vec<Item>, Vec<ParseError>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
 
+    for (line, parts) in records(data) {
         let kind = match parts[0] {
             "W" => ItemKind::Weapon,
             "A" => ItemKind::Armor,
             "C" => ItemKind::Accessory,
             "I" => ItemKind::Consumable,
-            _ => continue,
+            "Q" => ItemKind::Quest,
+            other => {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("unknown item type '{}'", other),
+                });
+                continue;
+            }
         };
 
-        let id = parts[1].to_string();
-        let name = parts[2].to_string();
-        let param1 = parts[3].parse().unwrap_or(0);
-        let param2 = parts[4].parse().unwrap_or(0);
-        let (param3, price) = if kind == ItemKind::Consumable {
-            (0, parts[4].parse().unwrap_or(0))
-        } else {
-            let p3 = parts.get(5).and_then(|s| s.parse().ok()).unwrap_or(0);
-            (param2, p3)
+        // 숫자 필드: 장비는 param1:param2:price, 소비는 param1:price, 퀘스트는 price
+        let min_fields = match kind {
+            ItemKind::Consumable => 5,
+            ItemKind::Quest => 4,
+            _ => 6,
+        };
+        if parts.len() < min_fields {
+            errors.push(too_short(line, min_fields, parts.len()));
+            continue;
+        }
+
+        let mut number = |index: usize, name: &str| field(parts[index], name, 0, line, &mut errors);
+        let (param1, param2, price) = match kind {
+            ItemKind::Consumable => (number(3, "param1"), 0, number(4, "price")),
+            ItemKind::Quest => (0, 0, number(3, "price")),
+            _ => (number(3, "param1"), number(4, "param2"), number(5, "price")),
+        };
+
+        let max_stack = match kind {
+            ItemKind::Consumable | ItemKind::Quest => parts
+                .get(min_fields)
+                .map(|s| field(s, "max_stack", DEFAULT_MAX_STACK, line, &mut errors))
+                .unwrap_or(DEFAULT_MAX_STACK)
+                .max(1),
+            _ => 1,
+        };
+        let status = match kind {
+            ItemKind::Consumable => parts
+                .get(min_fields + 1)
+                .and_then(|s| parse_status(s, line, &mut errors)),
+            _ => None,
         };
 
         items.push(Item {
-            id,
-            name,
+            id: parts[1].to_string(),
+            name: parts[2].to_string(),
             kind,
             param1,
-            param2: if kind == ItemKind::Consumable {
-                0
-            } else {
-                param2
-            },
-            param3,
+            param2,
             price,
+            max_stack,
+            status,
         });
     }
 
-    items
+    (items, errors)
 }
 
 pub fn parse_enemies(data: &str) -> Vec<Enemy> {
-    let mut enemies = Vec::new();
+    read_enemies(data).0
+}
 
-    for line in data.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
+pub fn try_parse_enemies(data: &str) -> Result<Vec<Enemy>, Vec<ParseError>> {
+    into_result(read_enemies(data))
+}
+
+fn read_enemies(data: &str) -> (Vec<Enemy>, Vec<ParseError>) {
+    let mut enemies = Vec::new();
+    let mut errors = Vec::new();
 
-        let parts: Vec<&str> = line.split(':').collect();
+    for (line, parts) in records(data) {
         if parts.len() < 7 {
+            errors.push(too_short(line, 7, parts.len()));
             continue;
         }
 
+        let (hit, ai_parts): (Vec<&str>, Vec<&str>) =
+            parts[7..].iter().partition(|p| p.starts_with("ON_HIT="));
+        let on_hit = hit.first().and_then(|text| {
+            let text = &text["ON_HIT=".len()..];
+            // 네 번째 값이 있으면 확률(%)
+            let (status, chance) = match text.rsplit_once('*') {
+                Some((status, chance)) if status.matches('*').count() == 2 => {
+                    (status, field(chance, "chance", 100, line, &mut errors))
+                }
+                _ => (text, 100),
+            };
+            parse_status(status, line, &mut errors).map(|effect| (effect, chance))
+        });
+
         enemies.push(Enemy {
             id: parts[0].to_string(),
             name: parts[1].to_string(),
-            hp: parts[2].parse().unwrap_or(0),
-            atk: parts[3].parse().unwrap_or(0),
-            def: parts[4].parse().unwrap_or(0),
-            exp: parts[5].parse().unwrap_or(0),
-            gold: parts[6].parse().unwrap_or(0),
+            hp: field(parts[2], "hp", 0, line, &mut errors),
+            atk: field(parts[3], "atk", 0, line, &mut errors),
+            def: field(parts[4], "def", 0, line, &mut errors),
+            exp: field(parts[5], "exp", 0, line, &mut errors),
+            gold: field(parts[6], "gold", 0, line, &mut errors),
+            ai: parse_enemy_ai(&ai_parts, line, &mut errors),
+            on_hit,
         });
     }
 
-    enemies
+    (enemies, errors)
+}
+
+/// KIND*power*ticks 형식의 상태 효과
+fn parse_status(text: &str, line: usize, errors: &mut Vec<ParseError>) -> Option<StatusEffect> {
+    let parts: Vec<&str> = text.split('*').collect();
+    if parts.len() != 3 {
+        errors.push(ParseError {
+            line,
+            reason: format!("expected KIND*power*ticks, found '{}'", text),
+        });
+        return None;
+    }
+
+    let kind = match parts[0] {
+        "POISON" => StatusKind::Poison,
+        "REGEN" => StatusKind::Regen,
+        "STUN" => StatusKind::Stun,
+        "ATK_UP" => StatusKind::AtkUp,
+        "DEF_UP" => StatusKind::DefUp,
+        other => {
+            errors.push(ParseError {
+                line,
+                reason: format!("unknown status effect '{}'", other),
+            });
+            return None;
+        }
+    };
+    Some(StatusEffect {
+        kind,
+        power: field(parts[1], "power", 0, line, errors),
+        ticks: field(parts[2], "ticks", 0, line, errors),
+    })
+}
+
+/// AI 이름과 숫자 인자. 비어 있으면 기본값, 인자가 빠지면 AGGRO의 기본 반경을 쓴다
+fn parse_enemy_ai(parts: &[&str], line: usize, errors: &mut Vec<ParseError>) -> EnemyAi {
+    let Some(kind) = parts.first() else {
+        return EnemyAi::default();
+    };
+    let mut param = |index: usize, name: &str, default: usize| {
+        parts
+            .get(index)
+            .map(|s| field(s, name, default, line, errors))
+            .unwrap_or(default)
+    };
+
+    match *kind {
+        "IDLE" => EnemyAi::Idle,
+        "WANDER" => EnemyAi::Wander,
+        "AGGRO" => EnemyAi::Aggro {
+            radius: param(1, "radius", 6),
+            leash: param(2, "leash", 12),
+        },
+        "FLEE" => EnemyAi::Flee {
+            radius: param(1, "radius", 6),
+            hp_percent: param(2, "hp%", 30) as i32,
+        },
+        "RANGED" => EnemyAi::Ranged {
+            radius: param(1, "radius", 6),
+            range: param(2, "range", 3),
+        },
+        other => {
+            errors.push(ParseError {
+                line,
+                reason: format!("unknown enemy ai '{}'", other),
+            });
+            EnemyAi::default()
+        }
+    }
+}
+
+pub fn parse_bosses(data: &str) -> Vec<Boss> {
+    read_bosses(data).0
+}
+
+pub fn try_parse_bosses(data: &str) -> Result<Vec<Boss>, Vec<ParseError>> {
+    into_result(read_bosses(data))
+}
+
+fn read_bosses(data: &str) -> (Vec<Boss>, Vec<ParseError>) {
+    let mut bosses = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
+        if parts.len() < 5 || !(parts.len() - 1).is_multiple_of(4) {
+            errors.push(ParseError {
+                line,
+                reason: "expected enemy_id followed by hp%:atk%:cooldown:pattern groups".into(),
+            });
+            continue;
+        }
+
+        let mut phases: Vec<BossPhase> = Vec::new();
+        for group in parts[1..].chunks(4) {
+            let pattern = match group[3] {
+                "MELEE" => BossPattern::Melee,
+                "SLAM" => BossPattern::Slam,
+                "CHARGE" => BossPattern::Charge,
+                other => {
+                    errors.push(ParseError {
+                        line,
+                        reason: format!("unknown boss pattern '{}'", other),
+                    });
+                    BossPattern::Melee
+                }
+            };
+            let phase = BossPhase {
+                hp_percent: field(group[0], "hp%", 100, line, &mut errors),
+                atk_percent: field(group[1], "atk%", 100, line, &mut errors),
+                cooldown: field(group[2], "cooldown", 30, line, &mut errors),
+                pattern,
+            };
+
+            // 단계는 체력 기준이 높은 것부터 와야 한다
+            let expected_first = phases.is_empty() && phase.hp_percent != 100;
+            let out_of_order = phases
+                .last()
+                .is_some_and(|prev| phase.hp_percent >= prev.hp_percent);
+            if expected_first || out_of_order {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("phase at {}% is out of order", phase.hp_percent),
+                });
+            }
+            phases.push(phase);
+        }
+
+        bosses.push(Boss {
+            enemy_id: parts[0].to_string(),
+            phases,
+        });
+    }
+
+    (bosses, errors)
+}
+
+pub fn parse_levels(data: &str) -> Vec<LevelGrowth> {
+    read_levels(data).0
+}
+
+pub fn try_parse_levels(data: &str) -> Result<Vec<LevelGrowth>, Vec<ParseError>> {
+    into_result(read_levels(data))
+}
+
+fn read_levels(data: &str) -> (Vec<LevelGrowth>, Vec<ParseError>) {
+    let mut levels: Vec<LevelGrowth> = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
+        if parts.len() < 6 {
+            errors.push(too_short(line, 6, parts.len()));
+            continue;
+        }
+
+        let growth = LevelGrowth {
+            level: field(parts[0], "level", 0, line, &mut errors),
+            exp: field(parts[1], "exp", 0, line, &mut errors),
+            hp: field(parts[2], "hp", 0, line, &mut errors),
+            mp: field(parts[3], "mp", 0, line, &mut errors),
+            atk: field(parts[4], "atk", 0, line, &mut errors),
+            def: field(parts[5], "def", 0, line, &mut errors),
+        };
+
+        // 레벨은 2부터 빠짐없이 이어져야 한다
+        let expected = levels.last().map_or(2, |prev| prev.level + 1);
+        if growth.level != expected {
+            errors.push(ParseError {
+                line,
+                reason: format!("expected level {}, found {}", expected, growth.level),
+            });
+            continue;
+        }
+        if growth.exp <= 0 {
+            errors.push(ParseError {
+                line,
+                reason: format!("exp must be positive, found {}", growth.exp),
+            });
+        }
+        levels.push(growth);
+    }
+
+    (levels, errors)
 }
 
 pub fn parse_maps(data: &str) -> Vec<Map> {
+    read_maps(data).0
+}
+
+pub fn try_parse_maps(data: &str) -> Result<Vec<Map>, Vec<ParseError>> {
+    into_result(read_maps(data))
+}
+
+fn read_maps(data: &str) -> (Vec<Map>, Vec<ParseError>) {
     let mut maps = Vec::new();
+    let mut errors = Vec::new();
     let mut current_map: Option<MapBuilder> = None;
+    // 모든 맵을 읽은 뒤 확인할 연결 (줄 번호, 도착 맵, 도착 지점)
+    let mut links: Vec<(usize, String, Option<String>)> = Vec::new();
 
-    for line in data.lines() {
+    for (i, line) in data.lines().enumerate() {
+        let line_no = i + 1;
         let line = line.trim();
 
         if let Some(rest) = line.strip_prefix("@MAP:") {
-            if let Some(builder) = current_map.take()
-                && let Some(map) = builder.build()
-            {
-                maps.push(map);
+            if let Some(builder) = current_map.take() {
+                finish_map(builder, &mut maps, &mut errors);
             }
 
             let parts: Vec<&str> = rest.split(':').collect();
@@ -107,72 +362,301 @@ pub fn parse_maps(data: &str) -> Vec<Map> {
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| id.clone());
 
-            current_map = Some(MapBuilder::new(id, name));
+            current_map = Some(MapBuilder::new(id, name, line_no));
         } else if line == "@END" {
-            if let Some(builder) = current_map.take()
-                && let Some(map) = builder.build()
-            {
-                maps.push(map);
+            if let Some(builder) = current_map.take() {
+                finish_map(builder, &mut maps, &mut errors);
             }
+        } else if line.starts_with('@') && current_map.is_none() {
+            errors.push(ParseError {
+                line: line_no,
+                reason: format!("'{}' outside of a @MAP block", line),
+            });
         } else if let Some(rest) = line.strip_prefix("@ENCOUNTERS:") {
             if let Some(ref mut builder) = current_map {
                 let parts: Vec<&str> = rest.split(':').collect();
+                if !parts.len().is_multiple_of(2) {
+                    errors.push(ParseError {
+                        line: line_no,
+                        reason: "expected enemy_id:weight pairs".into(),
+                    });
+                }
                 let mut i = 0;
                 while i + 1 < parts.len() {
                     let enemy_id = parts[i].to_string();
-                    let weight = parts[i + 1].parse().unwrap_or(1);
+                    let weight = field(parts[i + 1], "weight", 1, line_no, &mut errors);
                     builder.encounters.push((enemy_id, weight));
                     i += 2;
                 }
             }
-        } else if let Some(rest) = line.strip_prefix("@NEXT:") {
+        } else if let Some(rest) = line.strip_prefix("@ENEMY_CAP:") {
+            if let Some(ref mut builder) = current_map {
+                builder.enemy_cap = Some(field(rest, "enemy_cap", 0, line_no, &mut errors));
+            }
+        } else if line == "@TOWN" {
+            if let Some(ref mut builder) = current_map {
+                builder.town = true;
+            }
+        } else if let Some(rest) = line.strip_prefix("@RESPAWN:") {
+            if let Some(ref mut builder) = current_map {
+                builder.respawn_ticks = Some(field(rest, "respawn", 0, line_no, &mut errors));
+            }
+        } else if let Some(rest) = line.strip_prefix("@TREASURE:") {
+            if let Some(ref mut builder) = current_map
+                && let Some(link) = parse_link(rest, line_no, &mut errors)
+            {
+                builder.treasures.push(link);
+            }
+        } else if let Some(rest) = line.strip_prefix("@BOSS:") {
+            if let Some(ref mut builder) = current_map
+                && let Some(link) = parse_link(rest, line_no, &mut errors)
+            {
+                builder.bosses.push(link);
+            }
+        } else if let Some(rest) = line.strip_prefix("@PATROL:") {
             if let Some(ref mut builder) = current_map {
                 let parts: Vec<&str> = rest.split(':').collect();
-                if parts.len() >= 3 {
-                    let x = parts[0].parse().unwrap_or(0);
-                    let y = parts[1].parse().unwrap_or(0);
-                    let target = parts[2].to_string();
-                    builder.exits.push((x, y, target));
+                if parts.len() < 3 || parts.len().is_multiple_of(2) {
+                    errors.push(ParseError {
+                        line: line_no,
+                        reason: "expected enemy_id followed by x:y pairs".into(),
+                    });
+                } else {
+                    let points = parts[1..]
+                        .chunks(2)
+                        .map(|p| {
+                            let x = field(p[0], "x", 0, line_no, &mut errors);
+                            let y = field(p[1], "y", 0, line_no, &mut errors);
+                            (x, y)
+                        })
+                        .collect();
+                    builder.patrols.push((parts[0].to_string(), points));
                 }
             }
-        } else if let Some(rest) = line.strip_prefix("@DUNGEON:") {
+        } else if let Some(rest) = line.strip_prefix("@GATE:") {
+            if let Some(ref mut builder) = current_map
+                && let Some((x, y, condition)) = parse_link(rest, line_no, &mut errors)
+            {
+                let conditions = parse_conditions(&condition, line_no, &mut errors);
+                builder.gates.push((x, y, conditions));
+            }
+        } else if let Some(rest) = line.strip_prefix("@TRIGGER:") {
+            if let Some(ref mut builder) = current_map
+                && let Some(trigger) = parse_trigger(rest, line_no, &mut errors)
+            {
+                builder.triggers.push(trigger);
+            }
+        } else if let Some(rest) = line.strip_prefix("@SPAWN:") {
             if let Some(ref mut builder) = current_map {
                 let parts: Vec<&str> = rest.split(':').collect();
-                if parts.len() >= 3 {
-                    let x = parts[0].parse().unwrap_or(0);
-                    let y = parts[1].parse().unwrap_or(0);
-                    let target = parts[2].to_string();
-                    builder.dungeons.push((x, y, target));
+                if parts.len() < 3 {
+                    errors.push(ParseError {
+                        line: line_no,
+                        reason: "expected name:x:y".into(),
+                    });
+                } else {
+                    let x = field(parts[1], "x", 0, line_no, &mut errors);
+                    let y = field(parts[2], "y", 0, line_no, &mut errors);
+                    builder.spawns.push((parts[0].to_string(), x, y));
                 }
             }
+        } else if let Some(rest) = line.strip_prefix("@NEXT:") {
+            if let Some(ref mut builder) = current_map
+                && let Some(link) = parse_map_link(rest, line_no, &mut errors)
+            {
+                links.push((line_no, link.target.clone(), link.spawn.clone()));
+                builder.exits.push(link);
+            }
+        } else if let Some(rest) = line.strip_prefix("@DUNGEON:") {
+            if let Some(ref mut builder) = current_map
+                && let Some(link) = parse_map_link(rest, line_no, &mut errors)
+            {
+                links.push((line_no, link.target.clone(), link.spawn.clone()));
+                builder.dungeons.push(link);
+            }
+        } else if line.starts_with('@') {
+            errors.push(ParseError {
+                line: line_no,
+                reason: format!("unknown map directive '{}'", line),
+            });
         } else if !line.is_empty()
-            && !line.starts_with('#')
             && let Some(ref mut builder) = current_map
         {
+            // 맵 블록 안에서는 '#'도 벽 타일이므로 주석으로 취급하지 않는다
             builder.add_row(line);
         }
     }
 
-    if let Some(builder) = current_map
-        && let Some(map) = builder.build()
-    {
-        maps.push(map);
+    if let Some(builder) = current_map {
+        finish_map(builder, &mut maps, &mut errors);
+    }
+
+    for (line, target, spawn) in links {
+        let Some(map) = maps.iter().find(|m| m.id == target) else {
+            errors.push(ParseError {
+                line,
+                reason: format!("unknown target map '{}'", target),
+            });
+            continue;
+        };
+        if let Some(spawn) = spawn
+            && map.spawn_point(&spawn).is_none()
+        {
+            errors.push(ParseError {
+                line,
+                reason: format!("map '{}' has no spawn point '{}'", target, spawn),
+            });
+        }
     }
+    errors.sort_by_key(|e| e.line);
 
-    maps
+    (maps, errors)
 }
 
-pub fn parse_npcs(data: &str) -> Vec<Npc> {
-    let mut npcs = Vec::new();
+fn finish_map(builder: MapBuilder, maps: &mut Vec<Map>, errors: &mut Vec<ParseError>) {
+    let line = builder.line;
+    match builder.build() {
+        Some(map) => maps.push(map),
+        None => errors.push(ParseError {
+            line,
+            reason: "map has no tile rows".into(),
+        }),
+    }
+}
 
-    for line in data.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
+fn parse_link(
+    rest: &str,
+    line: usize,
+    errors: &mut Vec<ParseError>,
+) -> Option<(usize, usize, String)> {
+    let parts: Vec<&str> = rest.split(':').collect();
+    if parts.len() < 3 {
+        errors.push(ParseError {
+            line,
+            reason: "expected x:y:target_id".into(),
+        });
+        return None;
+    }
+
+    let x = field(parts[0], "x", 0, line, errors);
+    let y = field(parts[1], "y", 0, line, errors);
+    Some((x, y, parts[2].to_string()))
+}
+
+/// x:y:target_map[:spawn]
+fn parse_map_link(rest: &str, line: usize, errors: &mut Vec<ParseError>) -> Option<MapLink> {
+    let (x, y, target) = parse_link(rest, line, errors)?;
+    let spawn = rest.split(':').nth(3).map(|s| s.to_string());
+    Some(MapLink {
+        x,
+        y,
+        target,
+        spawn,
+    })
+}
+
+pub(super) fn parse_trigger(
+    rest: &str,
+    line: usize,
+    errors: &mut Vec<ParseError>,
+) -> Option<MapTrigger> {
+    let parts: Vec<&str> = rest.splitn(5, ':').collect();
+    if parts.len() < 5 {
+        errors.push(ParseError {
+            line,
+            reason: "expected x:y:ONCE|ALWAYS:condition:actions".into(),
+        });
+        return None;
+    }
+
+    let once = match parts[2] {
+        "ONCE" => true,
+        "ALWAYS" => false,
+        other => {
+            errors.push(ParseError {
+                line,
+                reason: format!("unknown trigger mode '{}'", other),
+            });
+            return None;
+        }
+    };
+
+    let actions: Vec<TriggerAction> = parts[4]
+        .split(';')
+        .filter_map(|action| {
+            let (name, value) = action.split_once('=').unwrap_or((action, ""));
+            match name {
+                "DIALOG" if !value.is_empty() => Some(TriggerAction::Dialog(value.to_string())),
+                "TELEPORT" | "BOSS" => {
+                    let Some((id, pos)) = map_position(value) else {
+                        errors.push(ParseError {
+                            line,
+                            reason: format!("invalid position in '{}'", action),
+                        });
+                        return None;
+                    };
+                    // ALWAYS 트리거의 보스는 밟을 때마다 하나씩 더 나타난다
+                    if name == "BOSS" && !once {
+                        errors.push(ParseError {
+                            line,
+                            reason: "BOSS is only allowed in ONCE triggers".into(),
+                        });
+                        return None;
+                    }
+                    Some(match name {
+                        "TELEPORT" => TriggerAction::Teleport(id, pos),
+                        _ => TriggerAction::Boss(id, pos),
+                    })
+                }
+                _ => parse_action(action, line, errors).map(TriggerAction::Script),
+            }
+        })
+        .collect();
+    if actions.is_empty() {
+        errors.push(ParseError {
+            line,
+            reason: "trigger has no actions".into(),
+        });
+        return None;
+    }
+
+    Some(MapTrigger {
+        x: field(parts[0], "x", 0, line, errors),
+        y: field(parts[1], "y", 0, line, errors),
+        once,
+        conditions: parse_conditions(parts[3], line, errors),
+        actions,
+    })
+}
+
+/// `id` 또는 `id@x,y` 형식. 좌표가 잘못되면 None
+fn map_position(s: &str) -> Option<(String, Option<(usize, usize)>)> {
+    match s.split_once('@') {
+        Some((id, pos)) => {
+            let (x, y) = pos.split_once(',')?;
+            let pos = (x.parse().ok()?, y.parse().ok()?);
+            Some((id.to_string(), Some(pos)))
         }
+        None if !s.is_empty() => Some((s.to_string(), None)),
+        None => None,
+    }
+}
+
+pub fn parse_npcs(data: &str) -> Vec<Npc> {
+    read_npcs(data).0
+}
 
-        let parts: Vec<&str> = line.split(':').collect();
+pub fn try_parse_npcs(data: &str) -> Result<Vec<Npc>, Vec<ParseError>> {
+    into_result(read_npcs(data))
+}
+
+fn read_npcs(data: &str) -> (Vec<Npc>, Vec<ParseError>) {
+    let mut npcs = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
         if parts.len() < 6 {
+            errors.push(too_short(line, 6, parts.len()));
             continue;
         }
 
@@ -181,65 +665,100 @@ pub fn parse_npcs(data: &str) -> Vec<Npc> {
             "S" => NpcType::ShopKeeper,
             "Q" => NpcType::QuestGiver,
             "H" => NpcType::Healer,
-            _ => NpcType::Villager,
+            "I" => NpcType::Inn,
+            other => {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("unknown npc type '{}'", other),
+                });
+                NpcType::Villager
+            }
         };
 
         npcs.push(Npc {
+            id: parts[0].to_string(),
             name: parts[1].to_string(),
             map_id: parts[2].to_string(),
             npc_type,
-            x: parts[4].parse().unwrap_or(0),
-            y: parts[5].parse().unwrap_or(0),
+            x: field(parts[4], "x", 0, line, &mut errors),
+            y: field(parts[5], "y", 0, line, &mut errors),
             dialog_id: parts.get(6).map(|s| s.to_string()).unwrap_or_default(),
-            shop_id: parts.get(7).map(|s| s.to_string()),
+            shop_id: parts
+                .get(7)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+            conditions: parts
+                .get(8)
+                .map(|s| parse_conditions(s, line, &mut errors))
+                .unwrap_or_default(),
         });
     }
 
-    npcs
+    (npcs, errors)
 }
 
 pub fn parse_dialogs(data: &str) -> Vec<Dialog> {
+    read_dialogs(data).0
+}
+
+pub fn try_parse_dialogs(data: &str) -> Result<Vec<Dialog>, Vec<ParseError>> {
+    into_result(read_dialogs(data))
+}
+
+fn read_dialogs(data: &str) -> (Vec<Dialog>, Vec<ParseError>) {
     let mut dialogs = Vec::new();
+    let mut errors = Vec::new();
     let mut current: Option<DialogBuilder> = None;
 
-    for line in data.lines() {
+    for (i, line) in data.lines().enumerate() {
+        let line_no = i + 1;
         let line = line.trim();
 
         if let Some(rest) = line.strip_prefix("@DIALOG:") {
             if let Some(builder) = current.take() {
-                dialogs.push(builder.build());
+                dialogs.push(builder.build(&mut errors));
             }
             current = Some(DialogBuilder::new(rest.to_string()));
         } else if line == "@END" {
             if let Some(builder) = current.take() {
-                dialogs.push(builder.build());
+                dialogs.push(builder.build(&mut errors));
+            }
+        } else if !line.is_empty() && !line.starts_with('#') {
+            match current {
+                Some(ref mut builder) if line.starts_with('@') => {
+                    builder.add_directive(line, line_no, &mut errors)
+                }
+                Some(ref mut builder) => builder.add_line(line, line_no, &mut errors),
+                None => errors.push(ParseError {
+                    line: line_no,
+                    reason: "dialog line outside of a @DIALOG block".into(),
+                }),
             }
-        } else if !line.is_empty()
-            && !line.starts_with('#')
-            && let Some(ref mut builder) = current
-        {
-            builder.add_line(line);
         }
     }
 
     if let Some(builder) = current {
-        dialogs.push(builder.build());
+        dialogs.push(builder.build(&mut errors));
     }
 
-    dialogs
+    (dialogs, errors)
 }
 
 pub fn parse_quests(data: &str) -> Vec<Quest> {
-    let mut quests = Vec::new();
+    read_quests(data).0
+}
 
-    for line in data.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
+pub fn try_parse_quests(data: &str) -> Result<Vec<Quest>, Vec<ParseError>> {
+    into_result(read_quests(data))
+}
 
-        let parts: Vec<&str> = line.split(':').collect();
+fn read_quests(data: &str) -> (Vec<Quest>, Vec<ParseError>) {
+    let mut quests = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
         if parts.len() < 8 {
+            errors.push(too_short(line, 8, parts.len()));
             continue;
         }
 
@@ -248,7 +767,13 @@ pub fn parse_quests(data: &str) -> Vec<Quest> {
             "COLLECT" => QuestType::Collect,
             "TALK" => QuestType::Talk,
             "REACH" => QuestType::Reach,
-            _ => QuestType::Kill,
+            other => {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("unknown quest type '{}'", other),
+                });
+                QuestType::Kill
+            }
         };
 
         quests.push(Quest {
@@ -256,46 +781,256 @@ pub fn parse_quests(data: &str) -> Vec<Quest> {
             name: parts[1].to_string(),
             quest_type,
             target_id: parts[3].to_string(),
-            target_count: parts[4].parse().unwrap_or(1),
-            reward_exp: parts[5].parse().unwrap_or(0),
-            reward_gold: parts[6].parse().unwrap_or(0),
+            target_count: field(parts[4], "count", 1, line, &mut errors),
+            reward_exp: field(parts[5], "reward_exp", 0, line, &mut errors),
+            reward_gold: field(parts[6], "reward_gold", 0, line, &mut errors),
             reward_item: parts.get(8).map(|s| s.to_string()),
             description: parts[7].to_string(),
         });
     }
 
-    quests
+    (quests, errors)
 }
 
 pub fn parse_shops(data: &str) -> Vec<Shop> {
+    read_shops(data).0
+}
+
+pub fn try_parse_shops(data: &str) -> Result<Vec<Shop>, Vec<ParseError>> {
+    into_result(read_shops(data))
+}
+
+fn read_shops(data: &str) -> (Vec<Shop>, Vec<ParseError>) {
     let mut shops = Vec::new();
+    let mut errors = Vec::new();
 
-    for line in data.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
+    for (line, parts) in records(data) {
+        if parts.len() < 6 {
+            errors.push(too_short(line, 6, parts.len()));
             continue;
         }
 
-        let parts: Vec<&str> = line.split(':').collect();
-        if parts.len() < 3 {
+        let items = parts[5..]
+            .iter()
+            .map(|entry| match entry.split_once('*') {
+                Some((item_id, stock)) => (
+                    item_id.to_string(),
+                    Some(field(stock, "stock", 1, line, &mut errors)),
+                ),
+                None => (entry.to_string(), None),
+            })
+            .collect();
+
+        shops.push(Shop {
+            id: parts[0].to_string(),
+            name: parts[1].to_string(),
+            buy_rate: field(parts[2], "buy%", 100, line, &mut errors),
+            sell_rate: field(parts[3], "sell%", 50, line, &mut errors),
+            restock_ticks: field(parts[4], "restock", 0, line, &mut errors),
+            items,
+        });
+    }
+
+    (shops, errors)
+}
+
+pub fn parse_loot(data: &str) -> Vec<LootTable> {
+    read_loot(data).0
+}
+
+pub fn try_parse_loot(data: &str) -> Result<Vec<LootTable>, Vec<ParseError>> {
+    into_result(read_loot(data))
+}
+
+fn read_loot(data: &str) -> (Vec<LootTable>, Vec<ParseError>) {
+    let mut tables = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
+        if parts.len() < 4 {
+            errors.push(too_short(line, 4, parts.len()));
             continue;
         }
 
-        let items: Vec<String> = parts[2..].iter().map(|s| s.to_string()).collect();
+        let source = match parts[0] {
+            "E" => LootSource::Enemy,
+            "T" => LootSource::Treasure,
+            other => {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("unknown loot type '{}'", other),
+                });
+                continue;
+            }
+        };
+
+        let (pairs, rest) = parts[2..].as_chunks::<2>();
+        if !rest.is_empty() {
+            errors.push(ParseError {
+                line,
+                reason: "expected item_id:weight pairs".into(),
+            });
+        }
 
-        shops.push(Shop {
+        let entries = pairs
+            .iter()
+            .map(|[item_id, weight]| {
+                let item_id = (*item_id != "-").then(|| item_id.to_string());
+                (item_id, field(weight, "weight", 0, line, &mut errors))
+            })
+            .collect();
+
+        tables.push(LootTable {
+            source,
+            id: parts[1].to_string(),
+            entries,
+        });
+    }
+
+    (tables, errors)
+}
+
+pub fn parse_skills(data: &str) -> Vec<Skill> {
+    read_skills(data).0
+}
+
+pub fn try_parse_skills(data: &str) -> Result<Vec<Skill>, Vec<ParseError>> {
+    into_result(read_skills(data))
+}
+
+fn read_skills(data: &str) -> (Vec<Skill>, Vec<ParseError>) {
+    let mut skills = Vec::new();
+    let mut errors = Vec::new();
+
+    for (line, parts) in records(data) {
+        if parts.len() < 7 {
+            errors.push(too_short(line, 7, parts.len()));
+            continue;
+        }
+        if skills.len() == MAX_SKILLS {
+            errors.push(ParseError {
+                line,
+                reason: format!("more than {} skills", MAX_SKILLS),
+            });
+            continue;
+        }
+
+        let effect = match parts[4] {
+            "DAMAGE" => SkillEffect::Damage,
+            "HEAL" => SkillEffect::Heal,
+            "BUFF" => SkillEffect::Buff,
+            other => {
+                errors.push(ParseError {
+                    line,
+                    reason: format!("unknown skill effect '{}'", other),
+                });
+                continue;
+            }
+        };
+
+        skills.push(Skill {
             id: parts[0].to_string(),
             name: parts[1].to_string(),
-            items,
+            mp_cost: field(parts[2], "mp", 0, line, &mut errors),
+            level: field(parts[3], "level", 1, line, &mut errors),
+            effect,
+            power: field(parts[5], "power", 0, line, &mut errors),
+            area: field(parts[6], "area", 0, line, &mut errors),
+            status: parts
+                .get(7)
+                .and_then(|s| parse_status(s, line, &mut errors)),
         });
     }
 
-    shops
+    (skills, errors)
+}
+
+/// 주석과 빈 줄을 제외한 (줄 번호, ':'로 나눈 필드) 목록
+pub(super) fn records(data: &str) -> Vec<(usize, Vec<&str>)> {
+    data.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| (line_no, line.split(':').collect()))
+        .collect()
+}
+
+fn into_result<T>((values, errors): (Vec<T>, Vec<ParseError>)) -> Result<Vec<T>, Vec<ParseError>> {
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(errors)
+    }
+}
+
+fn too_short(line: usize, expected: usize, found: usize) -> ParseError {
+    ParseError {
+        line,
+        reason: format!("expected at least {} fields, found {}", expected, found),
+    }
+}
+
+fn field<T: FromStr>(
+    value: &str,
+    name: &str,
+    default: T,
+    line: usize,
+    errors: &mut Vec<ParseError>,
+) -> T {
+    match value.parse() {
+        Ok(v) => v,
+        Err(_) => {
+            errors.push(ParseError {
+                line,
+                reason: format!("invalid {} '{}'", name, value),
+            });
+            default
+        }
+    }
+}
+
+pub(super) fn parse_dialog_line(
+    line: &str,
+    line_no: usize,
+    errors: &mut Vec<ParseError>,
+) -> DialogLine {
+    let parts: Vec<&str> = line.splitn(3, ':').collect();
+
+    let (conditions, actions, text) = if parts.len() == 3 {
+        (
+            parse_conditions(parts[0], line_no, errors),
+            parse_actions(parts[1], line_no, errors),
+            parts[2].to_string(),
+        )
+    } else if parts.len() == 2 {
+        (
+            Vec::new(),
+            parse_actions(parts[0], line_no, errors),
+            parts[1].to_string(),
+        )
+    } else {
+        (Vec::new(), Vec::new(), line.to_string())
+    };
+
+    DialogLine {
+        text,
+        conditions,
+        actions,
+        choices: Vec::new(),
+        jump: None,
+        fail: None,
+    }
 }
 
+/// 대화를 끝내는 예약 라벨
+const END_LABEL: &str = "END";
+
 struct DialogBuilder {
     id: String,
     lines: Vec<DialogLine>,
+    labels: Vec<(String, usize)>,
+    // 블록이 끝난 뒤 확인할 라벨 참조 (줄 번호, 라벨)
+    targets: Vec<(usize, String)>,
 }
 
 impl DialogBuilder {
@@ -303,105 +1038,245 @@ impl DialogBuilder {
         Self {
             id,
             lines: Vec::new(),
+            labels: Vec::new(),
+            targets: Vec::new(),
         }
     }
 
-    fn add_line(&mut self, line: &str) {
-        let parts: Vec<&str> = line.splitn(3, ':').collect();
+    fn add_line(&mut self, line: &str, line_no: usize, errors: &mut Vec<ParseError>) {
+        self.lines.push(parse_dialog_line(line, line_no, errors));
+    }
 
-        let (condition, action, text) = if parts.len() == 3 {
-            (
-                Self::parse_condition(parts[0]),
-                Self::parse_action(parts[1]),
-                parts[2].to_string(),
-            )
-        } else if parts.len() == 2 {
-            (None, Self::parse_action(parts[0]), parts[1].to_string())
-        } else {
-            (None, None, line.to_string())
+    fn add_directive(&mut self, line: &str, line_no: usize, errors: &mut Vec<ParseError>) {
+        let mut error = |reason: String| {
+            errors.push(ParseError {
+                line: line_no,
+                reason,
+            })
         };
 
-        self.lines.push(DialogLine {
-            text,
-            condition,
-            action,
-        });
+        if let Some(label) = line.strip_prefix("@LABEL:") {
+            if label == END_LABEL || self.labels.iter().any(|(name, _)| name == label) {
+                error(format!("duplicate label '{}'", label));
+            } else {
+                self.labels.push((label.to_string(), self.lines.len()));
+            }
+        } else if let Some(rest) = line.strip_prefix("@CHOICE:") {
+            let Some((label, text)) = rest.split_once(':') else {
+                error("expected @CHOICE:label:text".into());
+                return;
+            };
+            let Some(prev) = self.lines.last_mut() else {
+                error("@CHOICE without a preceding line".into());
+                return;
+            };
+            prev.choices.push((label.to_string(), text.to_string()));
+            self.targets.push((line_no, label.to_string()));
+        } else if let Some(label) = line.strip_prefix("@JUMP:") {
+            let Some(prev) = self.lines.last_mut() else {
+                error("@JUMP without a preceding line".into());
+                return;
+            };
+            prev.jump = Some(label.to_string());
+            self.targets.push((line_no, label.to_string()));
+        } else if let Some(label) = line.strip_prefix("@FAIL:") {
+            let Some(prev) = self.lines.last_mut() else {
+                error("@FAIL without a preceding line".into());
+                return;
+            };
+            prev.fail = Some(label.to_string());
+            self.targets.push((line_no, label.to_string()));
+        } else {
+            error(format!("unknown directive '{}'", line));
+        }
     }
 
-    fn parse_condition(s: &str) -> Option<DialogCondition> {
-        let parts: Vec<&str> = s.split('=').collect();
-        if parts.len() != 2 {
-            return None;
+    fn build(self, errors: &mut Vec<ParseError>) -> Dialog {
+        for (line, label) in &self.targets {
+            if label != END_LABEL && !self.labels.iter().any(|(name, _)| name == label) {
+                errors.push(ParseError {
+                    line: *line,
+                    reason: format!("unknown label '{}'", label),
+                });
+            }
         }
+
+        Dialog {
+            id: self.id,
+            lines: self.lines,
+            labels: self.labels,
+        }
+    }
+}
+
+/// `&`로 묶은 조건 목록. `!`를 붙이면 부정
+fn parse_conditions(s: &str, line: usize, errors: &mut Vec<ParseError>) -> Vec<DialogCondition> {
+    if s.is_empty() {
+        return Vec::new();
+    }
+
+    s.split('&')
+        .filter_map(|part| match part.strip_prefix('!') {
+            Some(inner) => {
+                parse_condition(inner, line, errors).map(|c| DialogCondition::Not(Box::new(c)))
+            }
+            None => parse_condition(part, line, errors),
+        })
+        .collect()
+}
+
+/// `;`로 나눈 행동 목록
+fn parse_actions(s: &str, line: usize, errors: &mut Vec<ParseError>) -> Vec<DialogAction> {
+    if s.is_empty() {
+        return Vec::new();
+    }
+
+    s.split(';')
+        .filter_map(|part| parse_action(part, line, errors))
+        .collect()
+}
+
+fn parse_condition(s: &str, line: usize, errors: &mut Vec<ParseError>) -> Option<DialogCondition> {
+    let parts: Vec<&str> = s.split('=').collect();
+    let condition = if parts.len() != 2 {
+        None
+    } else {
         match parts[0] {
             "HAS_QUEST" => Some(DialogCondition::HasQuest(parts[1].to_string())),
             "QUEST_DONE" => Some(DialogCondition::QuestComplete(parts[1].to_string())),
-            "HAS_ITEM" => Some(DialogCondition::HasItem(parts[1].to_string())),
+            "QUEST_REWARDED" => Some(DialogCondition::QuestRewarded(parts[1].to_string())),
+            "HAS_ITEM" => {
+                item_count(parts[1]).map(|(id, count)| DialogCondition::HasItem(id, count))
+            }
             "HAS_GOLD" => parts[1].parse().ok().map(DialogCondition::HasGold),
+            "FLAG" => Some(DialogCondition::Flag(parts[1].to_string())),
+            "VAR_GE" => {
+                var_amount(parts[1]).map(|(name, value)| DialogCondition::VarAtLeast(name, value))
+            }
             _ => None,
         }
+    };
+
+    if condition.is_none() {
+        errors.push(ParseError {
+            line,
+            reason: format!("invalid condition '{}'", s),
+        });
     }
+    condition
+}
 
-    fn parse_action(s: &str) -> Option<DialogAction> {
-        let parts: Vec<&str> = s.split('=').collect();
-        if parts.is_empty() {
-            return None;
-        }
-        match parts[0] {
-            "GIVE_QUEST" => parts
-                .get(1)
-                .map(|id| DialogAction::GiveQuest(id.to_string())),
-            "COMPLETE_QUEST" => parts
-                .get(1)
-                .map(|id| DialogAction::CompleteQuest(id.to_string())),
-            "GIVE_ITEM" => parts
-                .get(1)
-                .map(|id| DialogAction::GiveItem(id.to_string())),
-            "TAKE_ITEM" => parts
-                .get(1)
-                .map(|id| DialogAction::TakeItem(id.to_string())),
-            "GIVE_GOLD" => parts
-                .get(1)
-                .and_then(|g| g.parse().ok())
-                .map(DialogAction::GiveGold),
-            "TAKE_GOLD" => parts
-                .get(1)
-                .and_then(|g| g.parse().ok())
-                .map(DialogAction::TakeGold),
-            "OPEN_SHOP" => parts
-                .get(1)
-                .map(|id| DialogAction::OpenShop(id.to_string())),
-            "HEAL" => Some(DialogAction::Heal),
-            _ => None,
-        }
+/// `id` 또는 `id*n` 형식의 아이템 수량
+fn item_count(s: &str) -> Option<(String, u32)> {
+    match s.split_once('*') {
+        Some((id, count)) => count
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .map(|n| (id.to_string(), n)),
+        None => Some((s.to_string(), 1)),
     }
+}
 
-    fn build(self) -> Dialog {
-        Dialog {
-            id: self.id,
-            lines: self.lines,
-        }
+/// `name` 또는 `name*n` 형식의 변수 값 (음수 허용)
+fn var_amount(s: &str) -> Option<(String, i32)> {
+    match s.split_once('*') {
+        Some((name, value)) => value.parse().ok().map(|n| (name.to_string(), n)),
+        None => Some((s.to_string(), 1)),
+    }
+}
+
+fn parse_action(s: &str, line: usize, errors: &mut Vec<ParseError>) -> Option<DialogAction> {
+    let parts: Vec<&str> = s.split('=').collect();
+    let action = match parts[0] {
+        "GIVE_QUEST" => parts
+            .get(1)
+            .map(|id| DialogAction::GiveQuest(id.to_string())),
+        "COMPLETE_QUEST" => parts
+            .get(1)
+            .map(|id| DialogAction::CompleteQuest(id.to_string())),
+        "GIVE_ITEM" => parts
+            .get(1)
+            .and_then(|s| item_count(s))
+            .map(|(id, count)| DialogAction::GiveItem(id, count)),
+        "TAKE_ITEM" => parts
+            .get(1)
+            .and_then(|s| item_count(s))
+            .map(|(id, count)| DialogAction::TakeItem(id, count)),
+        "GIVE_GOLD" => parts
+            .get(1)
+            .and_then(|g| g.parse().ok())
+            .map(DialogAction::GiveGold),
+        "TAKE_GOLD" => parts
+            .get(1)
+            .and_then(|g| g.parse().ok())
+            .map(DialogAction::TakeGold),
+        "OPEN_SHOP" => parts
+            .get(1)
+            .map(|id| DialogAction::OpenShop(id.to_string())),
+        "HEAL" => Some(DialogAction::Heal),
+        "REST" => parts
+            .get(1)
+            .and_then(|p| p.parse().ok())
+            .filter(|&p| p > 0)
+            .map(DialogAction::Rest),
+        "SET_FLAG" => parts
+            .get(1)
+            .map(|name| DialogAction::SetFlag(name.to_string())),
+        "INC_VAR" => parts
+            .get(1)
+            .and_then(|s| var_amount(s))
+            .map(|(name, amount)| DialogAction::IncVar(name, amount)),
+        _ => None,
+    };
+
+    if action.is_none() {
+        errors.push(ParseError {
+            line,
+            reason: format!("invalid action '{}'", s),
+        });
     }
+    action
 }
 
 struct MapBuilder {
     id: String,
+    line: usize,
     name: String,
     rows: Vec<String>,
     encounters: Vec<(String, i32)>,
-    exits: Vec<(usize, usize, String)>,
-    dungeons: Vec<(usize, usize, String)>,
+    enemy_cap: Option<usize>,
+    respawn_ticks: Option<u32>,
+    exits: Vec<MapLink>,
+    dungeons: Vec<MapLink>,
+    spawns: Vec<(String, usize, usize)>,
+    treasures: Vec<(usize, usize, String)>,
+    bosses: Vec<(usize, usize, String)>,
+    patrols: Vec<(String, Vec<(usize, usize)>)>,
+    gates: Vec<(usize, usize, Vec<DialogCondition>)>,
+    triggers: Vec<MapTrigger>,
+    town: bool,
 }
 
 impl MapBuilder {
-    fn new(id: String, name: String) -> Self {
+    fn new(id: String, name: String, line: usize) -> Self {
         Self {
             id,
+            line,
             name,
             rows: Vec::new(),
             encounters: Vec::new(),
+            enemy_cap: None,
+            respawn_ticks: None,
             exits: Vec::new(),
             dungeons: Vec::new(),
+            spawns: Vec::new(),
+            treasures: Vec::new(),
+            bosses: Vec::new(),
+            patrols: Vec::new(),
+            gates: Vec::new(),
+            triggers: Vec::new(),
+            town: false,
         }
     }
 
@@ -438,8 +1313,13 @@ impl MapBuilder {
 
         let mut exits = self.exits;
         for (x, y) in auto_exits {
-            if !exits.iter().any(|(ex, ey, _)| *ex == x && *ey == y) {
-                exits.push((x, y, String::new()));
+            if !exits.iter().any(|link| link.x == x && link.y == y) {
+                exits.push(MapLink {
+                    x,
+                    y,
+                    target: String::new(),
+                    spawn: None,
+                });
             }
         }
 
@@ -450,8 +1330,211 @@ impl MapBuilder {
             height,
             tiles,
             encounters: self.encounters,
+            enemy_cap: self.enemy_cap,
+            respawn_ticks: self.respawn_ticks,
             exits,
             dungeons: self.dungeons,
+            spawns: self.spawns,
+            treasures: self.treasures,
+            bosses: self.bosses,
+            patrols: self.patrols,
+            gates: self.gates,
+            triggers: self.triggers,
+            town: self.town,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_data_parses_without_errors() {
+        let data = include_str!("../../resources/data/items.dat");
+        assert!(try_parse_items(data).is_ok());
+        let data = include_str!("../../resources/data/enemies.dat");
+        assert!(try_parse_enemies(data).is_ok());
+        let data = include_str!("../../resources/data/maps.dat");
+        assert_eq!(try_parse_maps(data).map(|maps| maps.len()), Ok(5));
+        let data = include_str!("../../resources/data/npcs.dat");
+        assert!(try_parse_npcs(data).is_ok());
+        let data = include_str!("../../resources/data/dialogs.dat");
+        assert!(try_parse_dialogs(data).is_ok());
+        let data = include_str!("../../resources/data/quests.dat");
+        assert!(try_parse_quests(data).is_ok());
+        let data = include_str!("../../resources/data/shops.dat");
+        assert!(try_parse_shops(data).is_ok());
+        let data = include_str!("../../resources/data/loot.dat");
+        assert!(try_parse_loot(data).is_ok());
+        let data = include_str!("../../resources/data/skills.dat");
+        assert!(try_parse_skills(data).is_ok());
+        let data = include_str!("../../resources/data/bosses.dat");
+        assert!(try_parse_bosses(data).is_ok());
+        let data = include_str!("../../resources/data/levels.dat");
+        assert_eq!(try_parse_levels(data).map(|levels| levels.len()), Ok(19));
+    }
+
+    #[test]
+    fn reports_line_and_reason() {
+        let data =
+            "# 적 데이터\nslime:슬라임:15:4:1:5:3\n\ngoblin:고블린:2x:10:3:15:10\nbat:박쥐:12\n";
+
+        let errors = try_parse_enemies(data).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError {
+                    line: 4,
+                    reason: "invalid hp '2x'".into(),
+                },
+                ParseError {
+                    line: 5,
+                    reason: "expected at least 7 fields, found 3".into(),
+                },
+            ]
+        );
+        assert_eq!(parse_enemies(data).len(), 2);
+
+        // 필드가 모자란 아이템 줄은 기본값으로 채우지 않고 건너뛴다
+        let data = "I:potion:물약:30:10\nW:stick:막대:3\n";
+        assert_eq!(try_parse_items(data).unwrap_err()[0].line, 2);
+        let items = parse_items(data);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "potion");
+    }
+
+    #[test]
+    fn enemy_ai_defaults_and_params() {
+        let data = "slime:슬라임:15:4:1:5:3\n\
+                    spider:거미:20:8:2:12:6:RANGED:6:3:ON_HIT=POISON*2*300*50\n\
+                    rat:쥐:10:5:1:4:2:FLEE\n\
+                    bat:박쥐:12:6:0:6:2:SLEEP\n";
+
+        let (enemies, errors) = read_enemies(data);
+
+        assert_eq!(enemies[0].ai, EnemyAi::default());
+        assert_eq!(
+            enemies[1].ai,
+            EnemyAi::Ranged {
+                radius: 6,
+                range: 3
+            }
+        );
+        assert_eq!(
+            enemies[1].on_hit,
+            Some((
+                StatusEffect {
+                    kind: StatusKind::Poison,
+                    power: 2,
+                    ticks: 300
+                },
+                50
+            ))
+        );
+        assert_eq!(enemies[0].on_hit, None);
+        assert_eq!(
+            enemies[2].ai,
+            EnemyAi::Flee {
+                radius: 6,
+                hp_percent: 30
+            }
+        );
+        assert_eq!(
+            errors,
+            vec![ParseError {
+                line: 4,
+                reason: "unknown enemy ai 'SLEEP'".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn dialog_line_combines_conditions_and_actions() {
+        let mut errors = Vec::new();
+        let line = parse_dialog_line(
+            "HAS_GOLD=50&!HAS_ITEM=potion*2:TAKE_GOLD=50;GIVE_ITEM=potion:샀다",
+            1,
+            &mut errors,
+        );
+
+        assert!(errors.is_empty());
+        assert_eq!(line.text, "샀다");
+        assert!(matches!(
+            line.conditions.as_slice(),
+            [DialogCondition::HasGold(50), DialogCondition::Not(inner)]
+                if matches!(inner.as_ref(), DialogCondition::HasItem(id, 2) if id == "potion")
+        ));
+        assert!(matches!(
+            line.actions.as_slice(),
+            [DialogAction::TakeGold(50), DialogAction::GiveItem(id, 1)] if id == "potion"
+        ));
+
+        parse_dialog_line("HAS_GOLD=5&:HEAL:x", 2, &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+
+        let line = parse_dialog_line("REST=10:쉬었다", 3, &mut errors);
+        assert!(matches!(line.actions.as_slice(), [DialogAction::Rest(10)]));
+        parse_dialog_line("REST=0:x", 4, &mut errors);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn triggers_parse_mode_conditions_and_actions() {
+        let data = "@MAP:m\n...\n\
+                    @TRIGGER:1:0:ONCE:!FLAG=seen:DIALOG=intro;SET_FLAG=seen;TELEPORT=cave@3,4\n\
+                    @TRIGGER:2:0:SOMETIMES::HEAL\n\
+                    @TRIGGER:0:0:ALWAYS::BOSS=demon@x\n\
+                    @TRIGGER:1:0:ONCE::HEAL\n\
+                    @TRIGGER:0:0:ALWAYS::BOSS=demon\n\
+                    @END\n";
+
+        let (maps, errors) = read_maps(data);
+        let triggers = &maps[0].triggers;
+
+        // 같은 칸에도 ONCE 트리거를 여럿 둘 수 있다
+        assert_eq!(triggers.len(), 2);
+        assert!(triggers[0].once && triggers[1].once);
+        assert!(matches!(
+            triggers[0].conditions.as_slice(),
+            [DialogCondition::Not(_)]
+        ));
+        assert!(matches!(
+            triggers[0].actions.as_slice(),
+            [
+                TriggerAction::Dialog(_),
+                TriggerAction::Script(DialogAction::SetFlag(_)),
+                TriggerAction::Teleport(map, Some((3, 4))),
+            ] if map == "cave"
+        ));
+        let lines: Vec<_> = errors.iter().map(|e| e.line).collect();
+        // 모르는 모드, ALWAYS 트리거의 BOSS
+        assert_eq!(lines, vec![4, 5, 5, 7, 7]);
+    }
+
+    #[test]
+    fn links_must_reach_existing_spawn_points() {
+        let data = "@MAP:a\n>.>\n@SPAWN:back:1:0\n@NEXT:0:0:b:front\n@NEXT:2:0:c\n@END\n\
+                    @MAP:b\n.>\n@SPAWN:front:0:0\n@NEXT:1:0:a:side\n@END\n";
+
+        let (maps, errors) = read_maps(data);
+
+        assert_eq!(maps[0].exits[0].spawn.as_deref(), Some("front"));
+        assert_eq!(maps[1].spawn_point("front"), Some((0, 0)));
+        assert_eq!(
+            errors,
+            vec![
+                ParseError {
+                    line: 5,
+                    reason: "unknown target map 'c'".into(),
+                },
+                ParseError {
+                    line: 10,
+                    reason: "map 'a' has no spawn point 'side'".into(),
+                },
+            ]
+        );
+    }
+}
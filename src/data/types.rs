@@ -1,3 +1,4 @@
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 
@@ -8,6 +9,7 @@ pub enum ItemKind {
     Armor,      // A - 방어구
     Accessory,  // C - 악세서리
     Consumable, // I - 소비 아이템
+    Quest,      // Q - 퀘스트 아이템
 }
 
 /// 아이템 데이터
@@ -16,6 +18,11 @@ pub enum ItemKind {
 /// A:leather:가죽갑옷:3:0:150  (방어구: def:mdef:price)
 /// C:ring:힘의 반지:2:0:200   (악세서리: atk_bonus:def_bonus:price)
 /// I:potion:회복약:30:50      (소비: hp_restore:price)
+/// Q:wolf_fang:늑대이빨:5     (퀘스트: price)
+/// 소비/퀘스트 아이템은 끝에 최대 겹침 수를 붙일 수 있다 (기본 99)
+/// I:full_potion:완전회복약:999:500:3
+/// 소비 아이템은 그 뒤에 상태 효과를 붙일 수 있다
+/// I:antidote:해독제:0:15:99:POISON*0*0
 #[derive(Debug, Clone)]
 pub struct Item {
     pub id: String,
@@ -23,13 +30,43 @@ pub struct Item {
     pub kind: ItemKind,
     pub param1: i32,
     pub param2: i32,
-    pub param3: i32,
     pub price: i32,
+    pub max_stack: u32,
+    pub status: Option<StatusEffect>,
+}
+
+/// 시간이 지나면 풀리는 상태 효과
+/// 포맷: KIND*power*ticks
+/// POISON*2*300   (일정 간격마다 power만큼 피해)
+/// REGEN*3*300    (일정 간격마다 power만큼 회복)
+/// STUN*0*60      (움직이거나 공격하지 못함)
+/// ATK_UP*5*400   (공격력 +power, 음수면 약화)
+/// DEF_UP*5*400   (방어력 +power, 음수면 약화)
+/// 같은 종류는 새 효과로 바뀌고, ticks가 0이면 그 효과를 없앤다
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub power: i32,
+    pub ticks: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusKind {
+    Poison,
+    Regen,
+    Stun,
+    AtkUp,
+    DefUp,
 }
 
 /// 적 데이터
-/// 포맷: id:name:hp:atk:def:exp:gold
+/// 포맷: id:name:hp:atk:def:exp:gold[:AI[:param...]]
 /// slime:슬라임:20:5:2:10:5
+/// bat:박쥐:12:6:0:6:2:WANDER
+/// wolf:늑대:30:12:2:18:8:AGGRO:8:14
+/// AI를 생략하면 AGGRO:6:12
+/// ON_HIT=KIND*power*ticks[*chance%] 필드를 붙이면 공격이 맞았을 때 상태 효과를 건다
+/// spider:거미:20:8:2:12:6:RANGED:6:3:ON_HIT=POISON*2*300*50
 #[derive(Debug, Clone)]
 pub struct Enemy {
     pub id: String,
@@ -39,6 +76,99 @@ pub struct Enemy {
     pub def: i32,
     pub exp: i32,
     pub gold: i32,
+    pub ai: EnemyAi,
+    pub on_hit: Option<(StatusEffect, i32)>, // (효과, 확률%)
+}
+
+/// 적 행동 방식. 반경과 거리는 모두 맨해튼 거리
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnemyAi {
+    /// IDLE - 제자리에서 붙어 있는 플레이어만 공격
+    Idle,
+    /// WANDER - 출현 지점 근처를 돌아다님
+    Wander,
+    /// AGGRO:radius:leash - 가까워지면 쫓다가 출현 지점에서 leash보다 멀어지면 돌아감
+    Aggro { radius: usize, leash: usize },
+    /// FLEE:radius:hp% - 쫓다가 체력이 hp% 이하로 떨어지면 달아남
+    Flee { radius: usize, hp_percent: i32 },
+    /// RANGED:radius:range - range만큼 거리를 두고 멀리서 공격
+    Ranged { radius: usize, range: usize },
+}
+
+impl Default for EnemyAi {
+    fn default() -> Self {
+        EnemyAi::Aggro {
+            radius: 6,
+            leash: 12,
+        }
+    }
+}
+
+/// 보스 데이터. 기본 능력치는 enemies.dat의 같은 id를 쓴다.
+/// 포맷: enemy_id:hp%:atk%:cooldown:PATTERN[:hp%:atk%:cooldown:PATTERN...]
+/// boss_demon:100:100:30:MELEE:60:120:20:SLAM:25:150:30:CHARGE
+/// 체력이 hp% 이하로 떨어지면 그 단계로 넘어간다. 첫 단계는 100이다.
+#[derive(Debug, Clone)]
+pub struct Boss {
+    pub enemy_id: String,
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BossPhase {
+    pub hp_percent: i32,
+    pub atk_percent: i32,
+    pub cooldown: u32,
+    pub pattern: BossPattern,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossPattern {
+    Melee,  // MELEE - 붙어서 공격
+    Slam,   // SLAM - 두 칸 안을 내려친다
+    Charge, // CHARGE - 네 배 빠르게 움직이고 공격한다
+}
+
+/// 전리품 테이블
+/// 포맷: TYPE:id:item_id:weight:item_id:weight:...
+/// E:goblin:-:60:potion:30:iron_sword:10   (적 처치, "-"는 드롭 없음)
+/// T:cave_chest:potion:3:hi_potion:1       (보물상자, 맵의 @TREASURE로 연결)
+#[derive(Debug, Clone)]
+pub struct LootTable {
+    pub source: LootSource,
+    pub id: String,
+    pub entries: Vec<(Option<String>, i32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LootSource {
+    Enemy,    // E - 적 처치
+    Treasure, // T - 보물상자
+}
+
+/// 스킬 데이터
+/// 포맷: id:name:mp:level:EFFECT:power:area[:STATUS]
+/// power_strike:강타:4:1:DAMAGE:180:0   (피해: 공격력 대비 배율%, area 0은 바라보는 칸, 1 이상은 주변 반경)
+/// heal:치유:6:2:HEAL:30:0              (회복: 회복량)
+/// war_cry:함성:10:4:BUFF:5:400         (강화: 공격력 보너스, area 자리에 지속 틱)
+/// STATUS는 DAMAGE면 맞은 적에게, 그 밖에는 플레이어에게 거는 상태 효과
+#[derive(Debug, Clone)]
+pub struct Skill {
+    pub id: String,
+    pub name: String,
+    pub mp_cost: i32,
+    pub level: i32,
+    pub effect: SkillEffect,
+    pub power: i32,
+    pub area: i32,
+    pub status: Option<StatusEffect>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkillEffect {
+    Damage,
+    Heal,
+    Buff,
 }
 
 /// 맵 타일
@@ -95,7 +225,16 @@ impl Tile {
 /// #...H....H.....#
 /// ################
 /// @ENCOUNTERS:slime:3:goblin:1
-/// @NEXT:>:next_map_id
+/// @ENEMY_CAP:6          (선택: 동시에 존재하는 적 수 제한)
+/// @RESPAWN:600          (선택: 적이 줄었을 때 다시 나타나는 간격, 틱)
+/// @TREASURE:x:y:loot_id (선택: 보물상자 전리품, 없으면 "default")
+/// @BOSS:x:y:enemy_id    (선택: 보스 위치, 쓰러뜨리기 전에는 출구가 잠긴다)
+/// @PATROL:enemy_id:x:y:x:y... (선택: 첫 지점에 나타나 지점들을 차례로 돈다)
+/// @GATE:x:y:CONDITION   (선택: 조건을 만족해야 지나갈 수 있는 칸, 조건 문법은 대화와 같다)
+/// @TRIGGER:x:y:ONCE:CONDITION:ACTION;ACTION... (선택: 밟으면 실행되는 이벤트, MapTrigger 참고)
+/// @SPAWN:name:x:y       (선택: 이름 붙은 도착 지점)
+/// @NEXT:x:y:next_map_id[:spawn]     (spawn이 없으면 도착 맵의 P 타일에 선다)
+/// @DUNGEON:x:y:next_map_id[:spawn]
 /// @END
 #[derive(Debug, Clone)]
 pub struct Map {
@@ -105,8 +244,17 @@ pub struct Map {
     pub height: usize,
     pub tiles: Vec<Tile>,
     pub encounters: Vec<(String, i32)>,
-    pub exits: Vec<(usize, usize, String)>,
-    pub dungeons: Vec<(usize, usize, String)>,
+    pub enemy_cap: Option<usize>,
+    pub respawn_ticks: Option<u32>,
+    pub exits: Vec<MapLink>,
+    pub dungeons: Vec<MapLink>,
+    pub spawns: Vec<(String, usize, usize)>,
+    pub treasures: Vec<(usize, usize, String)>,
+    pub bosses: Vec<(usize, usize, String)>,
+    pub patrols: Vec<(String, Vec<(usize, usize)>)>,
+    pub gates: Vec<(usize, usize, Vec<DialogCondition>)>,
+    pub triggers: Vec<MapTrigger>,
+    pub town: bool, // 들어오면 쓰러졌을 때 돌아올 곳이 된다
 }
 
 impl Map {
@@ -117,6 +265,41 @@ impl Map {
         self.tiles[y * self.width + x]
     }
 
+    pub fn treasure_loot_id(&self, x: usize, y: usize) -> &str {
+        self.treasures
+            .iter()
+            .find(|(tx, ty, _)| *tx == x && *ty == y)
+            .map(|(_, _, id)| id.as_str())
+            .unwrap_or("default")
+    }
+
+    pub fn spawn_point(&self, name: &str) -> Option<(usize, usize)> {
+        self.spawns
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, x, y)| (*x, *y))
+    }
+
+    /// 문이 있는 칸의 통과 조건
+    pub fn gate_conditions(&self, x: usize, y: usize) -> Option<&[DialogCondition]> {
+        self.gates
+            .iter()
+            .find(|(gx, gy, _)| *gx == x && *gy == y)
+            .map(|(_, _, conditions)| conditions.as_slice())
+    }
+
+    pub fn enemy_spawn_points(&self) -> Vec<(usize, usize)> {
+        let mut points = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get_tile(x, y) == Tile::Enemy {
+                    points.push((x, y));
+                }
+            }
+        }
+        points
+    }
+
     pub fn find_player_start(&self) -> Option<(usize, usize)> {
         for y in 0..self.height {
             for x in 0..self.width {
@@ -129,12 +312,120 @@ impl Map {
     }
 }
 
+/// 다른 맵으로 가는 출구나 던전 입구
+#[derive(Debug, Clone)]
+pub struct MapLink {
+    pub x: usize,
+    pub y: usize,
+    pub target: String,
+    pub spawn: Option<String>, // 도착 맵의 @SPAWN 이름
+}
+
+/// 맵 이벤트 트리거
+/// 포맷: @TRIGGER:x:y:ONCE|ALWAYS:CONDITION:ACTION;ACTION...
+/// @TRIGGER:20:5:ONCE:!FLAG=saw_ruins:DIALOG=ruins_intro;SET_FLAG=saw_ruins
+/// @TRIGGER:3:3:ALWAYS::TELEPORT=cave@10,4
+/// 대화 행동 외에 DIALOG=dialog_id, TELEPORT=map_id[@x,y], BOSS=enemy_id[@x,y]를 쓸 수 있다.
+/// 조건이 맞지 않거나 행동을 모두 실행할 수 없으면 발동하지 않고, ONCE는 발동한 뒤에만 소모된다.
+#[derive(Debug, Clone)]
+pub struct MapTrigger {
+    pub x: usize,
+    pub y: usize,
+    pub once: bool,
+    pub conditions: Vec<DialogCondition>,
+    pub actions: Vec<TriggerAction>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TriggerAction {
+    Dialog(String),
+    Teleport(String, Option<(usize, usize)>),
+    Boss(String, Option<(usize, usize)>), // 위치가 없으면 플레이어가 바라보는 칸
+    Script(DialogAction),
+}
+
+/// 장착 장비 능력치 합계
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EquipmentStats {
+    pub weapon_atk: i32,
+    pub armor_def: i32,
+    pub accessory_atk: i32,
+    pub accessory_def: i32,
+    pub crit: i32,
+}
+
+impl EquipmentStats {
+    pub fn add_item(&mut self, item: &Item) {
+        match item.kind {
+            ItemKind::Weapon => {
+                self.weapon_atk += item.param1;
+                self.crit += item.param2;
+            }
+            ItemKind::Armor => {
+                self.armor_def += item.param1;
+            }
+            ItemKind::Accessory => {
+                self.accessory_atk += item.param1;
+                self.accessory_def += item.param2;
+            }
+            ItemKind::Consumable | ItemKind::Quest => {}
+        }
+    }
+
+    pub fn atk(&self) -> i32 {
+        self.weapon_atk + self.accessory_atk
+    }
+
+    pub fn def(&self) -> i32 {
+        self.armor_def + self.accessory_def
+    }
+}
+
+/// 레벨업 성장표 한 줄
+/// 포맷: level:exp:hp:mp:atk:def
+/// 2:100:10:5:2:1   (레벨 2에 오르려면 100, 오르면 최대 HP+10, 최대 MP+5, 공격력+2, 방어력+1)
+/// 레벨은 2부터 하나씩 늘어나고, 마지막 줄의 레벨이 최대 레벨이다
+#[derive(Debug, Clone, Copy)]
+pub struct LevelGrowth {
+    pub level: i32,
+    pub exp: i32,
+    pub hp: i32,
+    pub mp: i32,
+    pub atk: i32,
+    pub def: i32,
+}
+
+/// 한 번에 얻은 경험치로 오른 레벨과 늘어난 스탯의 합
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LevelUp {
+    pub from: i32,
+    pub to: i32,
+    pub hp: i32,
+    pub mp: i32,
+    pub atk: i32,
+    pub def: i32,
+}
+
+impl LevelUp {
+    /// 이어서 오른 레벨업을 하나로 합친다
+    pub fn then(self, next: LevelUp) -> LevelUp {
+        LevelUp {
+            from: self.from,
+            to: next.to,
+            hp: self.hp + next.hp,
+            mp: self.mp + next.mp,
+            atk: self.atk + next.atk,
+            def: self.def + next.def,
+        }
+    }
+}
+
 /// 플레이어 스탯
 #[derive(Debug, Clone)]
 pub struct PlayerStats {
     pub level: i32,
     pub exp: i32,
-    pub exp_to_next: i32,
+    pub exp_to_next: i32, // 최대 레벨이면 0
     pub max_hp: i32,
     pub current_hp: i32,
     pub max_mp: i32,
@@ -162,14 +453,12 @@ impl Default for PlayerStats {
 }
 
 impl PlayerStats {
-    pub fn total_atk(&self, weapon: Option<&Item>) -> i32 {
-        let weapon_atk = weapon.map(|w| w.param1).unwrap_or(0);
-        self.base_atk + weapon_atk
+    pub fn total_atk(&self, equipment: &EquipmentStats) -> i32 {
+        self.base_atk + equipment.atk()
     }
 
-    pub fn total_def(&self, armor: Option<&Item>) -> i32 {
-        let armor_def = armor.map(|a| a.param1).unwrap_or(0);
-        self.base_def + armor_def
+    pub fn total_def(&self, equipment: &EquipmentStats) -> i32 {
+        self.base_def + equipment.def()
     }
 
     pub fn heal(&mut self, amount: i32) {
@@ -184,31 +473,56 @@ impl PlayerStats {
         self.current_hp <= 0
     }
 
-    pub fn add_exp(&mut self, exp: i32) -> bool {
+    /// 경험치를 얻고 오를 수 있는 만큼 레벨을 올린다. 레벨이 오르지 않았으면 None
+    pub fn add_exp(&mut self, exp: i32, levels: &[LevelGrowth]) -> Option<LevelUp> {
+        let mut gained: Option<LevelUp> = None;
         self.exp += exp;
-        if self.exp >= self.exp_to_next {
-            self.level_up();
-            true
-        } else {
-            false
+
+        while let Some(next) = levels.iter().find(|l| l.level == self.level + 1)
+            && self.exp >= next.exp
+        {
+            let up = gained.get_or_insert(LevelUp {
+                from: self.level,
+                ..LevelUp::default()
+            });
+            up.to = next.level;
+            up.hp += next.hp;
+            up.mp += next.mp;
+            up.atk += next.atk;
+            up.def += next.def;
+
+            self.exp -= next.exp;
+            self.level = next.level;
+            self.max_hp += next.hp;
+            self.max_mp += next.mp;
+            self.base_atk += next.atk;
+            self.base_def += next.def;
+        }
+
+        if gained.is_some() {
+            self.current_hp = self.max_hp;
+            self.current_mp = self.max_mp;
         }
+        self.update_exp_to_next(levels);
+        gained
     }
 
-    fn level_up(&mut self) {
-        self.exp -= self.exp_to_next;
-        self.level += 1;
-        self.exp_to_next = self.level * 100;
-        self.max_hp += 10;
-        self.current_hp = self.max_hp;
-        self.max_mp += 5;
-        self.current_mp = self.max_mp;
-        self.base_atk += 2;
-        self.base_def += 1;
+    /// 성장표에서 다음 레벨까지 필요한 경험치를 다시 구한다.
+    /// 최대 레벨에서는 경험치가 더 쌓이지 않는다
+    pub fn update_exp_to_next(&mut self, levels: &[LevelGrowth]) {
+        match levels.iter().find(|l| l.level == self.level + 1) {
+            Some(next) => self.exp_to_next = next.exp,
+            None => {
+                self.exp = 0;
+                self.exp_to_next = 0;
+            }
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Npc {
+    pub id: String,
     pub name: String,
     pub map_id: String,
     pub x: usize,
@@ -216,6 +530,7 @@ pub struct Npc {
     pub npc_type: NpcType,
     pub dialog_id: String,
     pub shop_id: Option<String>,
+    pub conditions: Vec<DialogCondition>, // 모두 만족할 때만 나타난다
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -224,41 +539,73 @@ pub enum NpcType {
     ShopKeeper,
     QuestGiver,
     Healer,
+    Inn,
 }
 
+/// 대화 데이터
+/// @LABEL:name 은 다음 줄에 이름을 붙이고, @CHOICE:label:text 와 @JUMP:label 은
+/// 바로 앞 줄에 선택지와 이동을 붙인다. @FAIL:label 은 앞 줄의 행동을 실행할 수 없을 때 갈 곳이다.
+/// label 자리의 END 는 대화를 끝낸다.
 #[derive(Debug, Clone)]
 pub struct Dialog {
     pub id: String,
     pub lines: Vec<DialogLine>,
+    pub labels: Vec<(String, usize)>,
+}
+
+impl Dialog {
+    /// 라벨이 가리키는 줄. END나 없는 라벨이면 None
+    pub fn label_line(&self, label: &str) -> Option<usize> {
+        self.labels
+            .iter()
+            .find(|(name, _)| name == label)
+            .map(|(_, line)| *line)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct DialogLine {
     pub text: String,
-    pub condition: Option<DialogCondition>,
-    pub action: Option<DialogAction>,
+    pub conditions: Vec<DialogCondition>, // 모두 만족해야 보인다
+    pub actions: Vec<DialogAction>,       // 전부 실행되거나 하나도 실행되지 않는다
+    pub choices: Vec<(String, String)>,   // (label, text)
+    pub jump: Option<String>,
+    pub fail: Option<String>, // 행동을 실행할 수 없을 때 갈 라벨
 }
 
 #[derive(Debug, Clone)]
 pub enum DialogCondition {
     HasQuest(String),
     QuestComplete(String),
-    HasItem(String),
+    QuestRewarded(String),
+    HasItem(String, u32),
     HasGold(i32),
+    Flag(String),
+    VarAtLeast(String, i32),
+    Not(Box<DialogCondition>),
 }
 
 #[derive(Debug, Clone)]
 pub enum DialogAction {
     GiveQuest(String),
     CompleteQuest(String),
-    GiveItem(String),
-    TakeItem(String),
+    GiveItem(String, u32),
+    TakeItem(String, u32),
     GiveGold(i32),
     TakeGold(i32),
     OpenShop(String),
-    Heal,
+    Heal,      // 무료로 HP/MP만 채운다
+    Rest(i32), // 레벨 x 값만큼 골드를 내고 쉰다
+    SetFlag(String),
+    IncVar(String, i32),
 }
 
+/// 퀘스트 데이터
+/// 포맷: id:name:type:target_id:count:reward_exp:reward_gold:description[:reward_item]
+/// KILL: target_id = enemy_id
+/// COLLECT: target_id = item_id (이미 가진 아이템도 포함)
+/// TALK: target_id = npc_id
+/// REACH: target_id = map_id 또는 map_id@x,y
 #[derive(Debug, Clone)]
 pub struct Quest {
     pub id: String,
@@ -280,6 +627,19 @@ pub enum QuestType {
     Reach,
 }
 
+impl Quest {
+    /// REACH 퀘스트 목표 (map_id, 좌표)
+    pub fn reach_target(&self) -> (&str, Option<(usize, usize)>) {
+        if let Some((map_id, pos)) = self.target_id.split_once('@')
+            && let Some((x, y)) = pos.split_once(',')
+            && let (Ok(x), Ok(y)) = (x.parse(), y.parse())
+        {
+            return (map_id, Some((x, y)));
+        }
+        (&self.target_id, None)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct QuestProgress {
     pub quest_id: String,
@@ -288,9 +648,35 @@ pub struct QuestProgress {
     pub rewarded: bool,
 }
 
+/// 상점 데이터
+/// 포맷: id:name:buy%:sell%:restock:item_id[*stock]:item_id[*stock]...
+/// weapon_shop:무기 상점:100:50:1800:wooden_sword:iron_sword*3:potion*10
+/// buy%/sell%는 아이템 가격에 곱하는 비율, stock을 생략하면 무제한.
+/// restock 틱마다 팔린 아이템이 하나씩 다시 들어온다 (0이면 다시 들어오지 않음)
 #[derive(Debug, Clone)]
 pub struct Shop {
     pub id: String,
     pub name: String,
-    pub items: Vec<String>,
+    pub buy_rate: i32,
+    pub sell_rate: i32,
+    pub restock_ticks: u32,
+    pub items: Vec<(String, Option<u32>)>, // (item_id, 최대 재고)
+}
+
+impl Shop {
+    pub fn buy_price(&self, item: &Item) -> i32 {
+        item.price * self.buy_rate / 100
+    }
+
+    pub fn sell_price(&self, item: &Item) -> i32 {
+        item.price * self.sell_rate / 100
+    }
+
+    /// 재고 제한이 없으면 None
+    pub fn max_stock(&self, item_id: &str) -> Option<u32> {
+        self.items
+            .iter()
+            .find(|(id, _)| id == item_id)
+            .and_then(|(_, stock)| *stock)
+    }
 }
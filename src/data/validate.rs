@@ -0,0 +1,794 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::parser::{
+    MAX_SKILLS, parse_dialog_line, parse_enemies, parse_items, parse_loot, parse_maps, parse_npcs,
+    parse_quests, parse_shops, parse_trigger, records,
+};
+use super::types::{
+    DialogAction, DialogCondition, LootSource, Map, QuestType, Tile, TriggerAction,
+};
+
+/// 검증할 데이터 파일 원문
+pub struct ContentFiles<'a> {
+    pub items: &'a str,
+    pub enemies: &'a str,
+    pub bosses: &'a str,
+    pub maps: &'a str,
+    pub npcs: &'a str,
+    pub dialogs: &'a str,
+    pub quests: &'a str,
+    pub shops: &'a str,
+    pub loot: &'a str,
+    pub skills: &'a str,
+}
+
+/// 데이터 검증 결과 (line은 1부터 시작)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: &'static str,
+    pub line: usize,
+    pub field: &'static str,
+    pub problem: String,
+}
+
+/// 모든 데이터 파일의 id 참조를 교차 검증한다.
+/// 시작 맵은 maps.dat의 첫 번째 맵으로 본다.
+pub fn validate_content(files: &ContentFiles) -> Vec<Diagnostic> {
+    let mut v = Validator {
+        diagnostics: Vec::new(),
+        item_ids: parse_items(files.items).into_iter().map(|i| i.id).collect(),
+        enemy_ids: parse_enemies(files.enemies)
+            .into_iter()
+            .map(|e| e.id)
+            .collect(),
+        maps: parse_maps(files.maps),
+        npc_ids: parse_npcs(files.npcs).into_iter().map(|n| n.id).collect(),
+        dialog_ids: dialog_headers(files.dialogs)
+            .into_iter()
+            .map(|(_, id)| id.into())
+            .collect(),
+        quest_ids: parse_quests(files.quests)
+            .into_iter()
+            .map(|q| q.id)
+            .collect(),
+        shop_ids: parse_shops(files.shops).into_iter().map(|s| s.id).collect(),
+        treasure_ids: parse_loot(files.loot)
+            .into_iter()
+            .filter(|t| t.source == LootSource::Treasure)
+            .map(|t| t.id)
+            .collect(),
+    };
+
+    v.check_items(files.items);
+    v.check_enemies(files.enemies);
+    v.check_bosses(files.bosses);
+    v.check_maps(files.maps);
+    v.check_npcs(files.npcs);
+    v.check_dialogs(files.dialogs);
+    v.check_quests(files.quests);
+    v.check_shops(files.shops);
+    v.check_loot(files.loot);
+    v.check_skills(files.skills);
+
+    v.diagnostics
+}
+
+struct Validator {
+    diagnostics: Vec<Diagnostic>,
+    item_ids: Vec<String>,
+    enemy_ids: Vec<String>,
+    maps: Vec<Map>,
+    npc_ids: Vec<String>,
+    dialog_ids: Vec<String>,
+    quest_ids: Vec<String>,
+    shop_ids: Vec<String>,
+    treasure_ids: Vec<String>,
+}
+
+/// 다른 데이터 파일의 id를 가리키는 참조의 종류
+#[derive(Clone, Copy)]
+enum RefKind {
+    Item,
+    Enemy,
+    Npc,
+    Dialog,
+    Quest,
+    Shop,
+    TreasureLoot,
+}
+
+impl RefKind {
+    fn name(self) -> &'static str {
+        match self {
+            RefKind::Item => "item",
+            RefKind::Enemy => "enemy",
+            RefKind::Npc => "npc",
+            RefKind::Dialog => "dialog",
+            RefKind::Quest => "quest",
+            RefKind::Shop => "shop",
+            RefKind::TreasureLoot => "treasure loot",
+        }
+    }
+}
+
+impl Validator {
+    fn report(&mut self, file: &'static str, line: usize, field: &'static str, problem: String) {
+        self.diagnostics.push(Diagnostic {
+            file,
+            line,
+            field,
+            problem,
+        });
+    }
+
+    fn check_ref(
+        &mut self,
+        file: &'static str,
+        line: usize,
+        field: &'static str,
+        kind: RefKind,
+        id: &str,
+    ) {
+        let known = match kind {
+            RefKind::Item => &self.item_ids,
+            RefKind::Enemy => &self.enemy_ids,
+            RefKind::Npc => &self.npc_ids,
+            RefKind::Dialog => &self.dialog_ids,
+            RefKind::Quest => &self.quest_ids,
+            RefKind::Shop => &self.shop_ids,
+            RefKind::TreasureLoot => &self.treasure_ids,
+        };
+        if !known.iter().any(|k| k == id) {
+            self.report(
+                file,
+                line,
+                field,
+                format!("unknown {} id '{}'", kind.name(), id),
+            );
+        }
+    }
+
+    fn check_map_ref(&mut self, file: &'static str, line: usize, field: &'static str, id: &str) {
+        if !self.maps.iter().any(|m| m.id == id) {
+            self.report(file, line, field, format!("unknown map id '{}'", id));
+        }
+    }
+
+    fn check_duplicates(&mut self, file: &'static str, ids: &[(usize, &str)]) {
+        for (i, (line, id)) in ids.iter().enumerate() {
+            if ids[..i].iter().any(|(_, prev)| prev == id) {
+                self.report(file, *line, "id", format!("duplicate id '{}'", id));
+            }
+        }
+    }
+
+    fn check_items(&mut self, data: &str) {
+        let ids: Vec<_> = records(data)
+            .into_iter()
+            .filter_map(|(line, parts)| parts.get(1).map(|id| (line, *id)))
+            .collect();
+        self.check_duplicates("items.dat", &ids);
+    }
+
+    fn check_enemies(&mut self, data: &str) {
+        let ids: Vec<_> = records(data)
+            .into_iter()
+            .map(|(line, parts)| (line, parts[0]))
+            .collect();
+        self.check_duplicates("enemies.dat", &ids);
+    }
+
+    fn check_bosses(&mut self, data: &str) {
+        let ids: Vec<_> = records(data)
+            .into_iter()
+            .map(|(line, parts)| (line, parts[0]))
+            .collect();
+        self.check_duplicates("bosses.dat", &ids);
+        for (line, id) in ids {
+            self.check_ref("bosses.dat", line, "enemy_id", RefKind::Enemy, id);
+        }
+    }
+
+    fn check_shops(&mut self, data: &str) {
+        let records = records(data);
+        let ids: Vec<_> = records
+            .iter()
+            .map(|(line, parts)| (*line, parts[0]))
+            .collect();
+        self.check_duplicates("shops.dat", &ids);
+
+        for (line, parts) in &records {
+            for entry in parts.iter().skip(5) {
+                let item_id = entry.split_once('*').map_or(*entry, |(id, _)| id);
+                self.check_ref("shops.dat", *line, "item_id", RefKind::Item, item_id);
+            }
+        }
+    }
+
+    fn check_loot(&mut self, data: &str) {
+        let records = records(data);
+        // 적 전리품(E)과 상자 전리품(T)은 id를 따로 쓴다
+        let mut kinds: Vec<&str> = Vec::new();
+        for (_, parts) in &records {
+            if !kinds.contains(&parts[0]) {
+                kinds.push(parts[0]);
+            }
+        }
+        for kind in kinds {
+            let ids: Vec<_> = records
+                .iter()
+                .filter(|(_, parts)| parts[0] == kind)
+                .filter_map(|(line, parts)| parts.get(1).map(|id| (*line, *id)))
+                .collect();
+            self.check_duplicates("loot.dat", &ids);
+        }
+
+        for (line, parts) in &records {
+            if parts[0] == "E"
+                && let Some(enemy_id) = parts.get(1)
+            {
+                self.check_ref("loot.dat", *line, "id", RefKind::Enemy, enemy_id);
+            }
+            for item_id in parts.iter().skip(2).step_by(2) {
+                if *item_id != "-" {
+                    self.check_ref("loot.dat", *line, "item_id", RefKind::Item, item_id);
+                }
+            }
+        }
+    }
+
+    fn check_skills(&mut self, data: &str) {
+        let ids: Vec<_> = records(data)
+            .into_iter()
+            .map(|(line, parts)| (line, parts[0]))
+            .collect();
+        self.check_duplicates("skills.dat", &ids);
+
+        for (line, _) in ids.iter().skip(MAX_SKILLS) {
+            self.report(
+                "skills.dat",
+                *line,
+                "id",
+                format!("more than {} skills", MAX_SKILLS),
+            );
+        }
+    }
+
+    fn check_quests(&mut self, data: &str) {
+        let records = records(data);
+        let ids: Vec<_> = records
+            .iter()
+            .map(|(line, parts)| (*line, parts[0]))
+            .collect();
+        self.check_duplicates("quests.dat", &ids);
+
+        let quests = parse_quests(data);
+        for (line, parts) in &records {
+            let Some(quest) = quests.iter().find(|q| q.id == parts[0]) else {
+                continue;
+            };
+            match quest.quest_type {
+                QuestType::Kill => self.check_ref(
+                    "quests.dat",
+                    *line,
+                    "target_id",
+                    RefKind::Enemy,
+                    &quest.target_id,
+                ),
+                QuestType::Collect => self.check_ref(
+                    "quests.dat",
+                    *line,
+                    "target_id",
+                    RefKind::Item,
+                    &quest.target_id,
+                ),
+                QuestType::Talk => self.check_ref(
+                    "quests.dat",
+                    *line,
+                    "target_id",
+                    RefKind::Npc,
+                    &quest.target_id,
+                ),
+                QuestType::Reach => {
+                    let (map_id, _) = quest.reach_target();
+                    self.check_map_ref("quests.dat", *line, "target_id", map_id);
+                }
+            }
+            if let Some(item_id) = &quest.reward_item {
+                self.check_ref("quests.dat", *line, "reward_item", RefKind::Item, item_id);
+            }
+        }
+    }
+
+    fn check_npcs(&mut self, data: &str) {
+        let records = records(data);
+        let ids: Vec<_> = records
+            .iter()
+            .map(|(line, parts)| (*line, parts[0]))
+            .collect();
+        self.check_duplicates("npcs.dat", &ids);
+
+        let npcs = parse_npcs(data);
+        for ((line, _), npc) in records.iter().filter(|(_, p)| p.len() >= 6).zip(&npcs) {
+            match self.maps.iter().find(|m| m.id == npc.map_id) {
+                Some(map) => {
+                    if !map.get_tile(npc.x, npc.y).is_passable() {
+                        let problem =
+                            format!("npc placed on impassable tile ({}, {})", npc.x, npc.y);
+                        self.report("npcs.dat", *line, "position", problem);
+                    }
+                }
+                None => self.check_map_ref("npcs.dat", *line, "map_id", &npc.map_id),
+            }
+            if !npc.dialog_id.is_empty() {
+                self.check_ref(
+                    "npcs.dat",
+                    *line,
+                    "dialog_id",
+                    RefKind::Dialog,
+                    &npc.dialog_id,
+                );
+            }
+            if let Some(shop_id) = &npc.shop_id {
+                self.check_ref("npcs.dat", *line, "shop_id", RefKind::Shop, shop_id);
+            }
+            for condition in &npc.conditions {
+                self.check_condition("npcs.dat", *line, condition);
+            }
+        }
+    }
+
+    fn check_dialogs(&mut self, data: &str) {
+        let headers = dialog_headers(data);
+        self.check_duplicates("dialogs.dat", &headers);
+
+        let mut in_dialog = false;
+        for (i, line) in data.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+
+            if line.starts_with("@DIALOG:") {
+                in_dialog = true;
+                continue;
+            } else if line == "@END" {
+                in_dialog = false;
+                continue;
+            } else if !in_dialog
+                || line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with('@')
+            {
+                continue;
+            }
+
+            let parsed = parse_dialog_line(line, line_no, &mut Vec::new());
+            for condition in &parsed.conditions {
+                self.check_condition("dialogs.dat", line_no, condition);
+            }
+            for action in &parsed.actions {
+                self.check_action("dialogs.dat", line_no, action);
+            }
+        }
+    }
+
+    fn check_action(&mut self, file: &'static str, line_no: usize, action: &DialogAction) {
+        match action {
+            DialogAction::GiveQuest(id) | DialogAction::CompleteQuest(id) => {
+                self.check_ref(file, line_no, "action", RefKind::Quest, id)
+            }
+            DialogAction::GiveItem(id, _) | DialogAction::TakeItem(id, _) => {
+                self.check_ref(file, line_no, "action", RefKind::Item, id)
+            }
+            DialogAction::OpenShop(id) => {
+                self.check_ref(file, line_no, "action", RefKind::Shop, id)
+            }
+            DialogAction::GiveGold(_)
+            | DialogAction::TakeGold(_)
+            | DialogAction::Heal
+            | DialogAction::Rest(_)
+            | DialogAction::SetFlag(_)
+            | DialogAction::IncVar(_, _) => {}
+        }
+    }
+
+    fn check_condition(&mut self, file: &'static str, line_no: usize, condition: &DialogCondition) {
+        match condition {
+            DialogCondition::HasQuest(id)
+            | DialogCondition::QuestComplete(id)
+            | DialogCondition::QuestRewarded(id) => {
+                self.check_ref(file, line_no, "condition", RefKind::Quest, id)
+            }
+            DialogCondition::HasItem(id, _) => {
+                self.check_ref(file, line_no, "condition", RefKind::Item, id)
+            }
+            DialogCondition::HasGold(_)
+            | DialogCondition::Flag(_)
+            | DialogCondition::VarAtLeast(_, _) => {}
+            DialogCondition::Not(inner) => self.check_condition(file, line_no, inner),
+        }
+    }
+
+    fn check_maps(&mut self, data: &str) {
+        let mut headers: Vec<(usize, &str)> = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+
+            if let Some(rest) = line.strip_prefix("@MAP:") {
+                headers.push((line_no, rest.split(':').next().unwrap_or_default()));
+            } else if let Some(rest) = line.strip_prefix("@ENCOUNTERS:") {
+                for enemy_id in rest.split(':').step_by(2) {
+                    self.check_ref("maps.dat", line_no, "enemy_id", RefKind::Enemy, enemy_id);
+                }
+            } else if let Some(rest) = line.strip_prefix("@NEXT:") {
+                self.check_link(headers.last(), line_no, rest, Tile::Exit);
+            } else if let Some(rest) = line.strip_prefix("@DUNGEON:") {
+                self.check_link(headers.last(), line_no, rest, Tile::Dungeon);
+            } else if let Some(rest) = line.strip_prefix("@TREASURE:") {
+                self.check_link(headers.last(), line_no, rest, Tile::Treasure);
+            } else if let Some(rest) = line.strip_prefix("@BOSS:") {
+                self.check_boss(headers.last(), line_no, rest);
+            } else if let Some(rest) = line.strip_prefix("@PATROL:") {
+                self.check_patrol(headers.last(), line_no, rest);
+            } else if let Some(rest) = line.strip_prefix("@SPAWN:") {
+                self.check_spawn(headers.last(), line_no, rest);
+            } else if let Some(rest) = line.strip_prefix("@GATE:") {
+                self.check_gate(headers.last(), line_no, rest);
+            } else if let Some(rest) = line.strip_prefix("@TRIGGER:") {
+                self.check_trigger(headers.last(), line_no, rest);
+            }
+        }
+
+        self.check_duplicates("maps.dat", &headers);
+
+        for (line_no, map_id) in &headers {
+            let Some(map) = self.maps.iter().find(|m| m.id == *map_id) else {
+                continue;
+            };
+            let dangling = map
+                .exits
+                .iter()
+                .filter(|link| link.target.is_empty())
+                .map(|link| format!("exit tile ({}, {}) has no @NEXT target", link.x, link.y))
+                .collect::<Vec<_>>();
+            let has_default_chest = (0..map.height).any(|y| {
+                (0..map.width).any(|x| {
+                    map.get_tile(x, y) == Tile::Treasure && map.treasure_loot_id(x, y) == "default"
+                })
+            });
+            for problem in dangling {
+                self.report("maps.dat", *line_no, "exits", problem);
+            }
+
+            if has_default_chest {
+                self.check_ref(
+                    "maps.dat",
+                    *line_no,
+                    "treasures",
+                    RefKind::TreasureLoot,
+                    "default",
+                );
+            }
+        }
+
+        let reachable = self.reachable_maps();
+        for (line_no, map_id) in &headers {
+            if !reachable.iter().any(|id| id == map_id) {
+                let problem = format!("map '{}' is unreachable from the start map", map_id);
+                self.report("maps.dat", *line_no, "id", problem);
+            }
+        }
+    }
+
+    fn check_link(
+        &mut self,
+        header: Option<&(usize, &str)>,
+        line_no: usize,
+        rest: &str,
+        tile: Tile,
+    ) {
+        let parts: Vec<&str> = rest.split(':').collect();
+        if parts.len() < 3 {
+            self.report("maps.dat", line_no, "link", "expected x:y:target".into());
+            return;
+        }
+
+        if tile == Tile::Treasure {
+            self.check_ref(
+                "maps.dat",
+                line_no,
+                "loot_id",
+                RefKind::TreasureLoot,
+                parts[2],
+            );
+        } else {
+            self.check_map_ref("maps.dat", line_no, "target_map", parts[2]);
+            if let Some(spawn) = parts.get(3)
+                && let Some(target) = self.maps.iter().find(|m| m.id == parts[2])
+                && target.spawn_point(spawn).is_none()
+            {
+                let problem = format!("map '{}' has no spawn point '{}'", parts[2], spawn);
+                self.report("maps.dat", line_no, "spawn", problem);
+            }
+        }
+
+        let (Ok(x), Ok(y)) = (parts[0].parse::<usize>(), parts[1].parse::<usize>()) else {
+            self.report(
+                "maps.dat",
+                line_no,
+                "position",
+                "invalid coordinates".into(),
+            );
+            return;
+        };
+        if let Some((_, map_id)) = header
+            && let Some(map) = self.maps.iter().find(|m| m.id == *map_id)
+            && map.get_tile(x, y) != tile
+        {
+            let problem = format!(
+                "tile at ({}, {}) is {:?}, expected {:?}",
+                x,
+                y,
+                map.get_tile(x, y),
+                tile
+            );
+            self.report("maps.dat", line_no, "position", problem);
+        }
+    }
+
+    fn check_boss(&mut self, header: Option<&(usize, &str)>, line_no: usize, rest: &str) {
+        let parts: Vec<&str> = rest.split(':').collect();
+        if let Some(enemy_id) = parts.get(2) {
+            self.check_ref("maps.dat", line_no, "enemy_id", RefKind::Enemy, enemy_id);
+        }
+        let (Some(Ok(x)), Some(Ok(y))) = (
+            parts.first().map(|s| s.parse::<usize>()),
+            parts.get(1).map(|s| s.parse::<usize>()),
+        ) else {
+            self.report(
+                "maps.dat",
+                line_no,
+                "position",
+                "invalid coordinates".into(),
+            );
+            return;
+        };
+        if let Some((_, map_id)) = header
+            && let Some(map) = self.maps.iter().find(|m| m.id == *map_id)
+            && !map.get_tile(x, y).is_passable()
+        {
+            let problem = format!("boss on impassable tile ({}, {})", x, y);
+            self.report("maps.dat", line_no, "position", problem);
+        }
+    }
+
+    fn check_patrol(&mut self, header: Option<&(usize, &str)>, line_no: usize, rest: &str) {
+        let parts: Vec<&str> = rest.split(':').collect();
+        self.check_ref("maps.dat", line_no, "enemy_id", RefKind::Enemy, parts[0]);
+
+        let map = header.and_then(|(_, map_id)| self.maps.iter().find(|m| m.id == *map_id));
+        let mut problems = Vec::new();
+        for point in parts[1..].chunks(2) {
+            let (Ok(x), Some(Ok(y))) = (
+                point[0].parse::<usize>(),
+                point.get(1).map(|s| s.parse::<usize>()),
+            ) else {
+                problems.push("invalid coordinates".into());
+                continue;
+            };
+            if let Some(map) = map
+                && !map.get_tile(x, y).is_passable()
+            {
+                problems.push(format!("patrol point on impassable tile ({}, {})", x, y));
+            }
+        }
+        for problem in problems {
+            self.report("maps.dat", line_no, "position", problem);
+        }
+    }
+
+    fn check_spawn(&mut self, header: Option<&(usize, &str)>, line_no: usize, rest: &str) {
+        let parts: Vec<&str> = rest.split(':').collect();
+        let (Some(Ok(x)), Some(Ok(y))) = (
+            parts.get(1).map(|s| s.parse::<usize>()),
+            parts.get(2).map(|s| s.parse::<usize>()),
+        ) else {
+            self.report(
+                "maps.dat",
+                line_no,
+                "position",
+                "invalid coordinates".into(),
+            );
+            return;
+        };
+        if let Some((_, map_id)) = header
+            && let Some(map) = self.maps.iter().find(|m| m.id == *map_id)
+            && !map.get_tile(x, y).is_passable()
+        {
+            let problem = format!("spawn point on impassable tile ({}, {})", x, y);
+            self.report("maps.dat", line_no, "position", problem);
+        }
+    }
+
+    fn check_gate(&mut self, header: Option<&(usize, &str)>, line_no: usize, rest: &str) {
+        let parts: Vec<&str> = rest.split(':').collect();
+        let (Some(Ok(x)), Some(Ok(y))) = (
+            parts.first().map(|s| s.parse::<usize>()),
+            parts.get(1).map(|s| s.parse::<usize>()),
+        ) else {
+            self.report(
+                "maps.dat",
+                line_no,
+                "position",
+                "invalid coordinates".into(),
+            );
+            return;
+        };
+        let Some((_, map_id)) = header else {
+            return;
+        };
+        let Some(map) = self.maps.iter().find(|m| m.id == *map_id) else {
+            return;
+        };
+
+        let passable = map.get_tile(x, y).is_passable();
+        let conditions = map.gate_conditions(x, y).unwrap_or_default().to_vec();
+        if !passable {
+            let problem = format!("gate on impassable tile ({}, {})", x, y);
+            self.report("maps.dat", line_no, "position", problem);
+        }
+        for condition in &conditions {
+            self.check_condition("maps.dat", line_no, condition);
+        }
+    }
+
+    fn check_trigger(&mut self, header: Option<&(usize, &str)>, line_no: usize, rest: &str) {
+        let Some(trigger) = parse_trigger(rest, line_no, &mut Vec::new()) else {
+            self.report("maps.dat", line_no, "trigger", "invalid trigger".into());
+            return;
+        };
+
+        for condition in &trigger.conditions {
+            self.check_condition("maps.dat", line_no, condition);
+        }
+        for action in &trigger.actions {
+            match action {
+                TriggerAction::Dialog(id) => {
+                    self.check_ref("maps.dat", line_no, "action", RefKind::Dialog, id)
+                }
+                TriggerAction::Teleport(map_id, pos) => {
+                    self.check_map_ref("maps.dat", line_no, "action", map_id);
+                    self.check_trigger_position(line_no, map_id, *pos, "teleport");
+                }
+                TriggerAction::Boss(enemy_id, pos) => {
+                    self.check_ref("maps.dat", line_no, "action", RefKind::Enemy, enemy_id);
+                    if let Some((_, map_id)) = header {
+                        self.check_trigger_position(line_no, map_id, *pos, "boss");
+                    }
+                }
+                TriggerAction::Script(action) => self.check_action("maps.dat", line_no, action),
+            }
+        }
+    }
+
+    /// 트리거가 보내거나 불러내는 좌표가 그 맵 안의 지나갈 수 있는 칸인지
+    fn check_trigger_position(
+        &mut self,
+        line_no: usize,
+        map_id: &str,
+        pos: Option<(usize, usize)>,
+        what: &str,
+    ) {
+        let Some((x, y)) = pos else {
+            return;
+        };
+        if let Some(map) = self.maps.iter().find(|m| m.id == map_id)
+            && !map.get_tile(x, y).is_passable()
+        {
+            let problem = format!("{} to impassable tile ({}, {})", what, x, y);
+            self.report("maps.dat", line_no, "position", problem);
+        }
+    }
+
+    fn reachable_maps(&self) -> Vec<String> {
+        let mut reachable = Vec::new();
+        let mut queue = VecDeque::new();
+
+        if let Some(start) = self.maps.first() {
+            reachable.push(start.id.clone());
+            queue.push_back(start);
+        }
+
+        while let Some(map) = queue.pop_front() {
+            let links = map.exits.iter().chain(&map.dungeons);
+            for link in links {
+                let tile = map.get_tile(link.x, link.y);
+                if !matches!(tile, Tile::Exit | Tile::Dungeon) || reachable.contains(&link.target) {
+                    continue;
+                }
+                if let Some(next) = self.maps.iter().find(|m| m.id == link.target) {
+                    reachable.push(next.id.clone());
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+fn dialog_headers(data: &str) -> Vec<(usize, &str)> {
+    data.lines()
+        .enumerate()
+        .filter_map(|(i, line)| line.trim().strip_prefix("@DIALOG:").map(|id| (i + 1, id)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled_content() -> ContentFiles<'static> {
+        ContentFiles {
+            items: include_str!("../../resources/data/items.dat"),
+            enemies: include_str!("../../resources/data/enemies.dat"),
+            bosses: include_str!("../../resources/data/bosses.dat"),
+            maps: include_str!("../../resources/data/maps.dat"),
+            npcs: include_str!("../../resources/data/npcs.dat"),
+            dialogs: include_str!("../../resources/data/dialogs.dat"),
+            quests: include_str!("../../resources/data/quests.dat"),
+            shops: include_str!("../../resources/data/shops.dat"),
+            loot: include_str!("../../resources/data/loot.dat"),
+            skills: include_str!("../../resources/data/skills.dat"),
+        }
+    }
+
+    #[test]
+    fn bundled_content_is_valid() {
+        let diagnostics = validate_content(&bundled_content());
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+    }
+
+    #[test]
+    fn reports_broken_references() {
+        let files = ContentFiles {
+            items: "I:potion:회복약:30:25\nI:potion:회복약:30:25\n",
+            shops: "shop:상점:100:50:0:potion:iron_armor*2\n",
+            loot: "E:phantom:-:50:elixir:50\nE:goblin:-:1\nT:goblin:-:1\nT:goblin:-:1\n",
+            quests: "q:퀘스트:KILL:phantom:1:0:0:설명:legendary_sword\n",
+            dialogs: "@DIALOG:d\nOPEN_SHOP=nowhere:안녕\n@END\n",
+            npcs: "n:NPC:town:V:0:0:d\n",
+            maps: "@MAP:town:마을\n###\n#P#\n###\n@TRIGGER:1:1:ONCE::DIALOG=missing\n\
+                   @TRIGGER:1:1:ALWAYS::TELEPORT=town@0,0\n@END\n\
+                   @MAP:island:섬\n.\n@END\n",
+            skills: "a:A:1:1:HEAL:1:0\nb:B:1:1:HEAL:1:0\nc:C:1:1:HEAL:1:0\n\
+                     d:D:1:1:HEAL:1:0\ne:E:1:1:HEAL:1:0\nf:F:1:1:HEAL:1:0\ng:G:1:1:HEAL:1:0\n",
+            ..bundled_content()
+        };
+
+        let problems: Vec<_> = validate_content(&files)
+            .into_iter()
+            .map(|d| (d.file, d.line, d.field))
+            .collect();
+
+        assert!(problems.contains(&("items.dat", 2, "id")));
+        assert!(problems.contains(&("shops.dat", 1, "item_id")));
+        assert!(problems.contains(&("quests.dat", 1, "target_id")));
+        assert!(problems.contains(&("quests.dat", 1, "reward_item")));
+        assert!(problems.contains(&("dialogs.dat", 2, "action")));
+        assert!(problems.contains(&("npcs.dat", 1, "position")));
+        assert!(problems.contains(&("maps.dat", 5, "action")));
+        assert!(problems.contains(&("maps.dat", 6, "position")));
+        assert!(problems.contains(&("maps.dat", 8, "id")));
+        assert!(problems.contains(&("loot.dat", 1, "id")));
+        assert!(problems.contains(&("loot.dat", 1, "item_id")));
+        assert!(!problems.contains(&("loot.dat", 3, "id")));
+        assert!(problems.contains(&("loot.dat", 4, "id")));
+        assert!(!problems.contains(&("skills.dat", 6, "id")));
+        assert!(problems.contains(&("skills.dat", 7, "id")));
+    }
+}
@@ -1,25 +1,39 @@
 mod combat;
+mod content_error;
 mod dialog;
 mod explore;
 mod inventory;
 mod menu;
+mod path;
 mod player;
 mod quest;
 mod renderer;
+mod rng;
 mod save;
 mod shop;
+mod slot;
 mod state;
+mod status;
 
-pub use combat::{CombatSystem, Direction};
+pub use combat::{CombatSystem, Direction, KillReward};
+pub use content_error::{ContentError, draw_content_errors};
 pub use dialog::draw_dialog;
-pub use explore::{TileEvent, check_tile_event, draw_explore};
-pub use inventory::{InventoryState, draw_inventory, draw_stats};
+pub use explore::{TileEvent, check_tile_event, draw_explore, draw_level_up, draw_notice};
+pub use inventory::{InventoryState, ItemAction, draw_inventory, draw_stats};
 pub use menu::draw_menu;
 pub use player::Player;
-pub use quest::draw_quest_log;
+pub use quest::{
+    draw_quest_log, record_kill, record_talk, update_collect_quests, update_reach_quests,
+};
 pub use renderer::{
-    COLOR_DARK_GRAY, COLOR_RED, COLOR_WHITE, clear_screen, draw_rect, draw_text, fill_rect,
+    COLOR_DARK_GRAY, COLOR_RED, COLOR_WHITE, FADE_STEPS, clear_screen, draw_fade, draw_rect,
+    draw_text, fill_rect,
+};
+pub use rng::Rng;
+pub use save::{SaveSummary, has_save_data, load_game, read_save_summaries, save_game};
+pub use shop::{ShopStock, draw_shop};
+pub use slot::draw_slot_select;
+pub use state::{
+    ContentErrorState, DialogState, GameState, MenuState, ShopMode, ShopState, ShopStep, SlotMode,
+    SlotSelectState,
 };
-pub use save::{has_save_data, load_game, save_game};
-pub use shop::draw_shop;
-pub use state::{DialogState, GameState, MenuState, ShopMode, ShopState};
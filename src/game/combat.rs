@@ -1,6 +1,17 @@
 use alloc::vec::Vec;
 
-use crate::data::{Enemy, Map, Tile};
+use super::path;
+use super::player::Player;
+use super::rng::Rng;
+use super::status::{STATUS_PULSE, StatusEffects};
+use crate::data::{
+    Boss, BossPattern, BossPhase, Enemy, EnemyAi, Map, Skill, SkillEffect, StatusEffect, StatusKind,
+};
+
+/// 길찾기에서 살펴보는 최대 거리
+const SEARCH_DIST: usize = path::MAX_SEARCH_DIST;
+/// WANDER 적이 출현 지점에서 벗어나는 최대 거리
+const WANDER_DIST: usize = 3;
 
 #[derive(Debug, Clone)]
 pub struct FieldEnemy {
@@ -10,6 +21,14 @@ pub struct FieldEnemy {
     pub hp: i32,
     pub attack_cooldown: u32,
     pub hit_flash: u32,
+    pub phases: Vec<BossPhase>, // 보스가 아니면 비어 있다
+    pub phase: usize,
+    pub home: (usize, usize),
+    pub patrol: Vec<(usize, usize)>, // @PATROL로 나온 적이 아니면 비어 있다
+    patrol_index: usize,
+    patrol_id: Option<usize>, // 맵의 @PATROL 중 몇 번째인지
+    returning: bool,          // leash를 벗어나 집으로 돌아가는 중
+    pub status: StatusEffects,
 }
 
 impl FieldEnemy {
@@ -22,6 +41,72 @@ impl FieldEnemy {
             hp,
             attack_cooldown: 0,
             hit_flash: 0,
+            phases: Vec::new(),
+            phase: 0,
+            home: (x, y),
+            patrol: Vec::new(),
+            patrol_index: 0,
+            patrol_id: None,
+            returning: false,
+            status: StatusEffects::default(),
+        }
+    }
+
+    pub fn is_boss(&self) -> bool {
+        !self.phases.is_empty()
+    }
+
+    fn current_phase(&self) -> Option<&BossPhase> {
+        self.phases.get(self.phase)
+    }
+
+    /// 남은 체력에 맞춰 보스 단계를 올린다. 단계가 바뀌면 true
+    fn update_phase(&mut self) -> bool {
+        let hp_percent = self.hp * 100 / self.data.hp.max(1);
+        let phase = self
+            .phases
+            .iter()
+            .rposition(|p| hp_percent <= p.hp_percent)
+            .unwrap_or(0);
+        if phase > self.phase {
+            self.phase = phase;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pattern(&self) -> BossPattern {
+        self.current_phase()
+            .map(|p| p.pattern)
+            .unwrap_or(BossPattern::Melee)
+    }
+
+    pub fn attack_range(&self) -> usize {
+        match self.pattern() {
+            BossPattern::Slam => 2,
+            BossPattern::Melee | BossPattern::Charge => match self.data.ai {
+                EnemyAi::Ranged { range, .. } => range,
+                _ => 1,
+            },
+        }
+    }
+
+    /// 몇 틱마다 움직이는지
+    fn move_interval(&self) -> u32 {
+        match self.pattern() {
+            BossPattern::Charge => 2,
+            BossPattern::Melee | BossPattern::Slam => 8,
+        }
+    }
+
+    fn kill_reward(&self) -> KillReward {
+        KillReward {
+            enemy_id: self.data.id.clone(),
+            exp: self.data.exp,
+            gold: self.data.gold,
+            boss: self.is_boss(),
+            patrol: self.patrol_id,
         }
     }
 
@@ -29,6 +114,10 @@ impl FieldEnemy {
         self.hp <= 0
     }
 
+    fn defense(&self) -> i32 {
+        self.data.def + self.status.power(StatusKind::DefUp)
+    }
+
     pub fn take_damage(&mut self, damage: i32) {
         self.hp = (self.hp - damage).max(0);
         self.hit_flash = 10;
@@ -38,7 +127,7 @@ impl FieldEnemy {
         self.x.abs_diff(px) + self.y.abs_diff(py)
     }
 
-    pub fn update(&mut self, player_x: usize, player_y: usize, map: &Map) {
+    pub fn update(&mut self, player_x: usize, player_y: usize, map: &Map, rng: &mut Rng) {
         if self.hit_flash > 0 {
             self.hit_flash -= 1;
         }
@@ -46,31 +135,110 @@ impl FieldEnemy {
             self.attack_cooldown -= 1;
         }
 
-        if self.distance_to(player_x, player_y) > 1 {
-            self.move_towards(player_x, player_y, map);
+        let player = (player_x, player_y);
+        let can_enter = |x, y| (x, y) != player && enemy_can_enter(map, x, y);
+        let distance = self.distance_to(player_x, player_y);
+
+        if (self.x, self.y) == self.home {
+            self.returning = false;
         }
-    }
 
-    fn move_towards(&mut self, target_x: usize, target_y: usize, map: &Map) {
-        let dx = match target_x.cmp(&self.x) {
-            core::cmp::Ordering::Greater => 1i32,
-            core::cmp::Ordering::Less => -1,
-            core::cmp::Ordering::Equal => 0,
-        };
-        let dy = match target_y.cmp(&self.y) {
-            core::cmp::Ordering::Greater => 1i32,
-            core::cmp::Ordering::Less => -1,
-            core::cmp::Ordering::Equal => 0,
+        let step = match self.data.ai {
+            EnemyAi::Idle => None,
+            EnemyAi::Wander => self.wander_step(rng, can_enter),
+            EnemyAi::Aggro { radius, leash } => {
+                if self.home.0.abs_diff(self.x) + self.home.1.abs_diff(self.y) > leash {
+                    self.returning = true;
+                }
+                if !self.returning && distance <= radius {
+                    self.step_towards(player, map, can_enter)
+                } else {
+                    self.idle_step(map, can_enter)
+                }
+            }
+            EnemyAi::Flee { radius, hp_percent } => {
+                if distance > radius {
+                    self.idle_step(map, can_enter)
+                } else if self.hp * 100 <= self.data.hp * hp_percent {
+                    self.step_away(player, can_enter)
+                } else {
+                    self.step_towards(player, map, can_enter)
+                }
+            }
+            EnemyAi::Ranged { radius, range } => {
+                if distance > radius {
+                    self.idle_step(map, can_enter)
+                } else if distance < range {
+                    self.step_away(player, can_enter)
+                } else if distance > range {
+                    self.step_towards(player, map, can_enter)
+                } else {
+                    None
+                }
+            }
         };
 
-        let new_x = (self.x as i32 + dx) as usize;
-        let new_y = (self.y as i32 + dy) as usize;
+        if let Some((x, y)) = step {
+            self.x = x;
+            self.y = y;
+        }
+    }
 
-        if dx != 0 && map.get_tile(new_x, self.y).is_passable() {
-            self.x = new_x;
-        } else if dy != 0 && map.get_tile(self.x, new_y).is_passable() {
-            self.y = new_y;
+    /// 붙어 있지 않으면 플레이어 쪽으로 한 칸
+    fn step_towards(
+        &self,
+        player: (usize, usize),
+        map: &Map,
+        can_enter: impl Fn(usize, usize) -> bool,
+    ) -> Option<(usize, usize)> {
+        if self.distance_to(player.0, player.1) <= 1 {
+            return None;
         }
+        path::next_step(map, (self.x, self.y), player, SEARCH_DIST, &can_enter)
+            .filter(|&(x, y)| can_enter(x, y))
+    }
+
+    /// 플레이어에게서 멀어지는 칸 하나
+    fn step_away(
+        &self,
+        player: (usize, usize),
+        can_enter: impl Fn(usize, usize) -> bool,
+    ) -> Option<(usize, usize)> {
+        let distance = self.distance_to(player.0, player.1);
+        path::neighbours((self.x, self.y))
+            .into_iter()
+            .find(|&(x, y)| {
+                can_enter(x, y) && x.abs_diff(player.0) + y.abs_diff(player.1) > distance
+            })
+    }
+
+    /// 쫓을 플레이어가 없을 때: 순찰로를 돌거나 출현 지점으로 돌아간다
+    fn idle_step(
+        &mut self,
+        map: &Map,
+        can_enter: impl Fn(usize, usize) -> bool,
+    ) -> Option<(usize, usize)> {
+        let target = if self.patrol.is_empty() {
+            self.home
+        } else {
+            if (self.x, self.y) == self.patrol[self.patrol_index] {
+                self.patrol_index = (self.patrol_index + 1) % self.patrol.len();
+            }
+            self.patrol[self.patrol_index]
+        };
+        path::next_step(map, (self.x, self.y), target, SEARCH_DIST, &can_enter)
+            .filter(|&(x, y)| can_enter(x, y))
+    }
+
+    /// 출현 지점 근처에서 아무 방향으로나 한 칸
+    fn wander_step(
+        &self,
+        rng: &mut Rng,
+        can_enter: impl Fn(usize, usize) -> bool,
+    ) -> Option<(usize, usize)> {
+        let (x, y) = path::neighbours((self.x, self.y))[rng.below(4) as usize];
+        (can_enter(x, y) && x.abs_diff(self.home.0) + y.abs_diff(self.home.1) <= WANDER_DIST)
+            .then_some((x, y))
     }
 
     pub fn can_attack(&self) -> bool {
@@ -78,8 +246,12 @@ impl FieldEnemy {
     }
 
     pub fn do_attack(&mut self) -> i32 {
-        self.attack_cooldown = 30;
-        self.data.atk
+        let (cooldown, atk_percent) = self
+            .current_phase()
+            .map(|p| (p.cooldown, p.atk_percent))
+            .unwrap_or((30, 100));
+        self.attack_cooldown = cooldown;
+        (self.data.atk + self.status.power(StatusKind::AtkUp)) * atk_percent / 100
     }
 }
 
@@ -88,7 +260,26 @@ pub struct CombatSystem {
     pub enemies: Vec<FieldEnemy>,
     pub player_attack_cooldown: u32,
     pub player_hit_flash: u32,
+    pub critical_flash: u32,
+    pub skill_flash: Option<SkillFlash>,
     update_counter: u32,
+    respawn_timer: u32,
+    spawn_point_count: usize, // 지금 맵의 출현 지점 수. 맵에 들어올 때 센다
+}
+
+/// 스킬 사용 시 화면에 잠깐 표시되는 효과 범위
+#[derive(Debug, Clone)]
+pub struct SkillFlash {
+    pub effect: SkillEffect,
+    pub tiles: Vec<(usize, usize)>,
+    pub ticks: u32,
+}
+
+/// 스킬 사용 결과. 회복량과 상태 효과는 호출한 쪽에서 플레이어에게 적용한다.
+pub struct SkillOutcome {
+    pub kills: Vec<KillReward>,
+    pub heal: i32,
+    pub status: Vec<StatusEffect>,
 }
 
 impl CombatSystem {
@@ -96,32 +287,116 @@ impl CombatSystem {
         Self::default()
     }
 
-    pub fn spawn_enemies(&mut self, map: &Map, enemy_data: &[Enemy]) {
+    pub fn spawn_enemies(
+        &mut self,
+        map: &Map,
+        enemy_data: &[Enemy],
+        player: &Player,
+        rng: &mut Rng,
+    ) {
         self.enemies.clear();
+        self.respawn_timer = 0;
 
-        for (enemy_id, _weight) in &map.encounters {
-            if let Some(data) = enemy_data.iter().find(|e| &e.id == enemy_id) {
-                for y in 0..map.height {
-                    for x in 0..map.width {
-                        if map.get_tile(x, y) == Tile::Enemy
-                            && !self.enemies.iter().any(|e| e.x == x && e.y == y)
-                        {
-                            self.enemies.push(FieldEnemy::new(data.clone(), x, y));
-                            break;
-                        }
-                    }
-                }
+        let mut points = map.enemy_spawn_points();
+        self.spawn_point_count = points.len();
+        let count = map.enemy_cap.unwrap_or(points.len()).min(points.len());
+
+        // 출현 지점을 섞은 뒤 앞에서부터 count개만 사용한다
+        for i in 0..count {
+            let j = i + rng.below((points.len() - i) as u32) as usize;
+            points.swap(i, j);
+        }
+
+        for &(x, y) in points.iter().take(count) {
+            if let Some(data) = pick_encounter(map, enemy_data, rng) {
+                self.enemies.push(FieldEnemy::new(data.clone(), x, y));
+            }
+        }
+
+        // 순찰하는 적은 첫 지점에 나타나고, 쓰러뜨리면 맵에 다시 들어와도 나타나지 않는다
+        for (i, (enemy_id, points)) in map.patrols.iter().enumerate() {
+            if player.is_patrol_defeated(&map.id, i) {
+                continue;
+            }
+            let data = enemy_data.iter().find(|e| e.id == *enemy_id);
+            if let (Some(data), Some(&(x, y))) = (data, points.first()) {
+                let mut enemy = FieldEnemy::new(data.clone(), x, y);
+                enemy.patrol = points.clone();
+                enemy.patrol_id = Some(i);
+                self.enemies.push(enemy);
             }
         }
     }
 
-    pub fn update(
+    /// 트리거로 정해진 적 하나를 불러낸다
+    pub fn spawn_enemy(&mut self, data: &Enemy, x: usize, y: usize) {
+        self.enemies.push(FieldEnemy::new(data.clone(), x, y));
+    }
+
+    pub fn spawn_boss(&mut self, data: &Enemy, boss: &Boss, x: usize, y: usize) {
+        let mut enemy = FieldEnemy::new(data.clone(), x, y);
+        enemy.phases = boss.phases.clone();
+        self.enemies.push(enemy);
+    }
+
+    /// 살아 있는 보스. 있으면 출구가 잠긴다
+    pub fn boss(&self) -> Option<&FieldEnemy> {
+        self.enemies.iter().find(|e| e.is_boss() && !e.is_dead())
+    }
+
+    fn respawn_enemies(
         &mut self,
         player_x: usize,
         player_y: usize,
-        player_def: i32,
         map: &Map,
+        enemy_data: &[Enemy],
+        rng: &mut Rng,
+    ) {
+        let Some(respawn_ticks) = map.respawn_ticks else {
+            return;
+        };
+
+        let cap = map.enemy_cap.unwrap_or(self.spawn_point_count);
+        let roaming = self.enemies.iter().filter(|e| e.patrol.is_empty()).count();
+        if roaming >= cap {
+            self.respawn_timer = 0;
+            return;
+        }
+
+        self.respawn_timer += 1;
+        if self.respawn_timer < respawn_ticks {
+            return;
+        }
+        self.respawn_timer = 0;
+
+        // 플레이어 바로 옆에서는 나타나지 않는다
+        let free: Vec<_> = map
+            .enemy_spawn_points()
+            .into_iter()
+            .filter(|&(x, y)| {
+                x.abs_diff(player_x) + y.abs_diff(player_y) > 3
+                    && !self.enemies.iter().any(|e| e.x == x && e.y == y)
+            })
+            .collect();
+        if free.is_empty() {
+            return;
+        }
+
+        let (x, y) = free[rng.below(free.len() as u32) as usize];
+        if let Some(data) = pick_encounter(map, enemy_data, rng) {
+            self.enemies.push(FieldEnemy::new(data.clone(), x, y));
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        player: &mut Player,
+        map: &Map,
+        enemy_data: &[Enemy],
+        rng: &mut Rng,
     ) -> CombatResult {
+        let (player_x, player_y) = (player.x, player.y);
+        let player_def = player.combat_def();
         self.update_counter = self.update_counter.wrapping_add(1);
 
         if self.player_attack_cooldown > 0 {
@@ -130,14 +405,28 @@ impl CombatSystem {
         if self.player_hit_flash > 0 {
             self.player_hit_flash -= 1;
         }
+        if self.critical_flash > 0 {
+            self.critical_flash -= 1;
+        }
+        if let Some(flash) = &mut self.skill_flash {
+            flash.ticks = flash.ticks.saturating_sub(1);
+            if flash.ticks == 0 {
+                self.skill_flash = None;
+            }
+        }
 
-        let mut damage_taken = 0;
+        let pulse = self.update_counter.is_multiple_of(STATUS_PULSE);
+        let status_hp = player.status.tick(pulse);
+        let mut damage_taken = (-status_hp).max(0);
+        let mut kills = Vec::new();
+        let mut enraged = None;
 
-        if self.update_counter.is_multiple_of(8) {
-            for enemy in &mut self.enemies {
-                if !enemy.is_dead() {
-                    enemy.update(player_x, player_y, map);
-                }
+        for enemy in &mut self.enemies {
+            if !enemy.is_dead()
+                && !enemy.status.is_stunned()
+                && self.update_counter.is_multiple_of(enemy.move_interval())
+            {
+                enemy.update(player_x, player_y, map, rng);
             }
         }
 
@@ -146,17 +435,52 @@ impl CombatSystem {
                 continue;
             }
 
-            if enemy.distance_to(player_x, player_y) <= 1 && enemy.can_attack() {
+            // 이번 틱에 풀리는 기절도 이번 틱까지는 막는다
+            let stunned = enemy.status.is_stunned();
+            match enemy.status.tick(pulse) {
+                hp if hp < 0 => enemy.take_damage(-hp),
+                hp => enemy.hp = (enemy.hp + hp).min(enemy.data.hp),
+            }
+            if enemy.is_dead() {
+                kills.push(enemy.kill_reward());
+                continue;
+            }
+            if enemy.update_phase() {
+                enraged = Some(enemy.data.name.clone());
+            }
+
+            if stunned {
+                continue;
+            }
+            // 멀리서 치는 공격은 벽 너머로 닿지 않는다
+            let distance = enemy.distance_to(player_x, player_y);
+            if distance <= enemy.attack_range()
+                && (distance <= 1
+                    || path::clear_line(map, (enemy.x, enemy.y), (player_x, player_y)))
+                && enemy.can_attack()
+            {
                 let raw_damage = enemy.do_attack();
-                let actual_damage = (raw_damage - player_def / 2).max(1);
+                let actual_damage = vary_damage((raw_damage - player_def / 2).max(1), rng);
                 damage_taken += actual_damage;
                 self.player_hit_flash = 10;
+
+                if let Some((effect, chance)) = enemy.data.on_hit
+                    && rng.percent(chance)
+                {
+                    player.status.apply(effect);
+                }
             }
         }
 
         self.enemies.retain(|e| !e.is_dead());
+        self.respawn_enemies(player_x, player_y, map, enemy_data, rng);
 
-        CombatResult { damage_taken }
+        CombatResult {
+            damage_taken,
+            healed: status_hp.max(0),
+            kills,
+            enraged,
+        }
     }
 
     pub fn player_attack(
@@ -164,7 +488,9 @@ impl CombatSystem {
         player_x: usize,
         player_y: usize,
         player_atk: i32,
+        crit_chance: i32,
         facing: Direction,
+        rng: &mut Rng,
     ) -> Option<KillReward> {
         if self.player_attack_cooldown > 0 {
             return None;
@@ -174,19 +500,15 @@ impl CombatSystem {
 
         for enemy in &mut self.enemies {
             if enemy.x == tx && enemy.y == ty && !enemy.is_dead() {
-                let damage = (player_atk - enemy.data.def / 2).max(1);
+                let mut damage = vary_damage((player_atk - enemy.defense() / 2).max(1), rng);
+                if rng.percent(crit_chance) {
+                    damage *= 2;
+                    self.critical_flash = 20;
+                }
                 enemy.take_damage(damage);
                 self.player_attack_cooldown = 15;
 
-                return if enemy.is_dead() {
-                    Some(KillReward {
-                        enemy_id: enemy.data.id.clone(),
-                        exp: enemy.data.exp,
-                        gold: enemy.data.gold,
-                    })
-                } else {
-                    None
-                };
+                return enemy.is_dead().then(|| enemy.kill_reward());
             }
         }
 
@@ -194,6 +516,69 @@ impl CombatSystem {
         None
     }
 
+    /// 스킬을 사용한다. 공격 쿨다운 중이면 None을 돌려주고 MP는 쓰지 않아야 한다.
+    pub fn cast_skill(
+        &mut self,
+        skill: &Skill,
+        player_x: usize,
+        player_y: usize,
+        player_atk: i32,
+        facing: Direction,
+        rng: &mut Rng,
+    ) -> Option<SkillOutcome> {
+        if self.player_attack_cooldown > 0 {
+            return None;
+        }
+        self.player_attack_cooldown = 15;
+
+        let mut outcome = SkillOutcome {
+            kills: Vec::new(),
+            heal: 0,
+            status: Vec::new(),
+        };
+        let mut tiles = Vec::new();
+
+        match skill.effect {
+            SkillEffect::Damage => {
+                tiles = skill_area(player_x, player_y, skill.area, facing);
+                let attack = player_atk * skill.power / 100;
+
+                for enemy in &mut self.enemies {
+                    if enemy.is_dead() || !tiles.contains(&(enemy.x, enemy.y)) {
+                        continue;
+                    }
+                    enemy.take_damage(vary_damage((attack - enemy.defense() / 2).max(1), rng));
+                    if enemy.is_dead() {
+                        outcome.kills.push(enemy.kill_reward());
+                    } else if let Some(effect) = skill.status {
+                        enemy.status.apply(effect);
+                    }
+                }
+            }
+            SkillEffect::Heal => {
+                tiles.push((player_x, player_y));
+                outcome.heal = skill.power;
+                outcome.status.extend(skill.status);
+            }
+            SkillEffect::Buff => {
+                tiles.push((player_x, player_y));
+                outcome.status.push(StatusEffect {
+                    kind: StatusKind::AtkUp,
+                    power: skill.power,
+                    ticks: skill.area.max(0) as u32,
+                });
+                outcome.status.extend(skill.status);
+            }
+        }
+
+        self.skill_flash = Some(SkillFlash {
+            effect: skill.effect,
+            tiles,
+            ticks: 12,
+        });
+        Some(outcome)
+    }
+
     pub fn enemy_at(&self, x: usize, y: usize) -> bool {
         self.enemies
             .iter()
@@ -201,14 +586,62 @@ impl CombatSystem {
     }
 }
 
+/// 맵의 @ENCOUNTERS 가중치에 따라 적 하나를 고른다
+fn pick_encounter<'a>(map: &Map, enemy_data: &'a [Enemy], rng: &mut Rng) -> Option<&'a Enemy> {
+    let table: Vec<_> = map
+        .encounters
+        .iter()
+        .filter_map(|(id, weight)| {
+            enemy_data
+                .iter()
+                .find(|e| e.id == *id)
+                .map(|data| (data, *weight))
+        })
+        .collect();
+    rng.pick_weighted(&table).copied()
+}
+
+/// 적은 열린 문이라도 지나가지 않는다
+fn enemy_can_enter(map: &Map, x: usize, y: usize) -> bool {
+    map.get_tile(x, y).is_passable() && map.gate_conditions(x, y).is_none()
+}
+
+/// area가 0이면 바라보는 칸, 아니면 플레이어 주변 반경 area 안의 칸들
+fn skill_area(px: usize, py: usize, area: i32, facing: Direction) -> Vec<(usize, usize)> {
+    if area <= 0 {
+        return alloc::vec![facing.apply(px, py)];
+    }
+
+    let r = area as usize;
+    let mut tiles = Vec::new();
+    for y in py.saturating_sub(r)..=py + r {
+        for x in px.saturating_sub(r)..=px + r {
+            if (x, y) != (px, py) {
+                tiles.push((x, y));
+            }
+        }
+    }
+    tiles
+}
+
+/// 기본 피해량에 ±10% 편차를 준다
+fn vary_damage(damage: i32, rng: &mut Rng) -> i32 {
+    (damage * rng.range(90, 110) / 100).max(1)
+}
+
 pub struct CombatResult {
     pub damage_taken: i32,
+    pub healed: i32,                            // 재생으로 회복한 체력
+    pub kills: Vec<KillReward>,                 // 독으로 쓰러진 적
+    pub enraged: Option<alloc::string::String>, // 이번 틱에 단계가 바뀐 보스 이름
 }
 
 pub struct KillReward {
     pub enemy_id: alloc::string::String,
     pub exp: i32,
     pub gold: i32,
+    pub boss: bool,
+    pub patrol: Option<usize>, // 쓰러뜨린 순찰 적의 @PATROL 순번
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -229,3 +662,214 @@ impl Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slime() -> Enemy {
+        Enemy {
+            id: "slime".into(),
+            name: "슬라임".into(),
+            hp: 1000,
+            atk: 4,
+            def: 2,
+            exp: 5,
+            gold: 3,
+            ai: EnemyAi::default(),
+            on_hit: None,
+        }
+    }
+
+    fn attack_sequence(seed: u64) -> Vec<i32> {
+        let mut rng = Rng::new(seed);
+        let mut combat = CombatSystem::new();
+        combat.enemies.push(FieldEnemy::new(slime(), 1, 0));
+
+        let mut hp = Vec::new();
+        for _ in 0..5 {
+            combat.player_attack_cooldown = 0;
+            combat.player_attack(0, 0, 20, 30, Direction::Right, &mut rng);
+            hp.push(combat.enemies[0].hp);
+        }
+        hp
+    }
+
+    #[test]
+    fn spawns_weighted_enemies_up_to_cap() {
+        let maps = crate::data::parse_maps(
+            "@MAP:field:들판\n#######\n#EEEEE#\n#EEEEE#\n#######\n@ENCOUNTERS:slime:1:ghost:0\n@ENEMY_CAP:6\n@END\n",
+        );
+        let player = Player::new("Hero".into(), "field");
+        let mut rng = Rng::new(99);
+        let mut combat = CombatSystem::new();
+
+        combat.spawn_enemies(&maps[0], &[slime()], &player, &mut rng);
+
+        assert_eq!(combat.enemies.len(), 6);
+        assert!(combat.enemies.iter().all(|e| e.data.id == "slime"));
+    }
+
+    #[test]
+    fn defeated_patrols_stay_gone() {
+        let maps = crate::data::parse_maps(
+            "@MAP:hall:복도\n.....\n@PATROL:slime:0:0:4:0\n@PATROL:slime:4:0:0:0\n@END\n",
+        );
+        let mut player = Player::new("Hero".into(), "hall");
+        let mut rng = Rng::new(1);
+        let mut combat = CombatSystem::new();
+
+        player.defeat_patrol("hall", 0);
+        combat.spawn_enemies(&maps[0], &[slime()], &player, &mut rng);
+
+        assert_eq!(combat.enemies.len(), 1);
+        assert_eq!((combat.enemies[0].x, combat.enemies[0].y), (4, 0));
+        assert_eq!(combat.enemies[0].kill_reward().patrol, Some(1));
+    }
+
+    #[test]
+    fn fixed_seed_gives_identical_damage() {
+        let first = attack_sequence(1234);
+        assert_eq!(first, attack_sequence(1234));
+
+        // 기본 피해 19의 ±10%, 치명타 시 두 배
+        let mut prev = 1000;
+        for hp in first {
+            let damage = prev - hp;
+            assert!((17..=20).contains(&damage) || (34..=40).contains(&damage));
+            prev = hp;
+        }
+    }
+
+    #[test]
+    fn area_skill_hits_enemies_in_radius() {
+        let skill = Skill {
+            id: "whirlwind".into(),
+            name: "회전베기".into(),
+            mp_cost: 8,
+            level: 3,
+            effect: SkillEffect::Damage,
+            power: 100,
+            area: 1,
+            status: None,
+        };
+        let mut weak = slime();
+        weak.hp = 1;
+
+        let mut combat = CombatSystem::new();
+        combat.enemies.push(FieldEnemy::new(weak.clone(), 4, 4));
+        combat.enemies.push(FieldEnemy::new(weak.clone(), 6, 6));
+        combat.enemies.push(FieldEnemy::new(weak, 7, 5));
+
+        let outcome = combat
+            .cast_skill(&skill, 5, 5, 20, Direction::Up, &mut Rng::new(3))
+            .unwrap();
+
+        assert_eq!(outcome.kills.len(), 2);
+        assert!(!combat.enemies[2].is_dead());
+        assert!(
+            combat
+                .cast_skill(&skill, 5, 5, 20, Direction::Up, &mut Rng::new(3))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn aggro_enemy_chases_then_returns_past_leash() {
+        let maps = crate::data::parse_maps("@MAP:hall\n..........\n.########.\n..........\n@END\n");
+        let mut rng = Rng::new(5);
+        let mut enemy = FieldEnemy::new(slime(), 0, 0);
+        enemy.data.ai = EnemyAi::Aggro {
+            radius: 4,
+            leash: 2,
+        };
+
+        // 멀리 있는 플레이어는 무시한다
+        enemy.update(9, 2, &maps[0], &mut rng);
+        assert_eq!((enemy.x, enemy.y), (0, 0));
+
+        // 가까워지면 쫓아간다
+        enemy.update(0, 2, &maps[0], &mut rng);
+        assert_eq!((enemy.x, enemy.y), (0, 1));
+
+        // 출현 지점에서 leash보다 멀어지면 플레이어가 옆에 있어도 돌아간다
+        (enemy.x, enemy.y) = (3, 0);
+        enemy.update(5, 0, &maps[0], &mut rng);
+        assert_eq!((enemy.x, enemy.y), (2, 0));
+    }
+
+    #[test]
+    fn boss_enters_phases_as_hp_drops() {
+        let boss = crate::data::parse_bosses("slime:100:100:30:MELEE:50:200:10:SLAM\n").remove(0);
+        let maps = crate::data::parse_maps("@MAP:arena\n.....\n@END\n");
+        let mut rng = Rng::new(7);
+        let mut combat = CombatSystem::new();
+        combat.spawn_boss(&slime(), &boss, 2, 0);
+
+        let mut player = Player::new("Hero".into(), "arena");
+        player.stats.base_def = 0;
+
+        let result = combat.update(&mut player, &maps[0], &[], &mut rng);
+        assert!(result.enraged.is_none());
+        assert_eq!(result.damage_taken, 0);
+
+        combat.enemies[0].hp = 500;
+        let result = combat.update(&mut player, &maps[0], &[], &mut rng);
+        assert_eq!(result.enraged.as_deref(), Some("슬라임"));
+        assert_eq!(combat.enemies[0].attack_range(), 2);
+        // 두 칸 떨어져서 내려친다. 공격력 4의 200%
+        assert!((7..=9).contains(&result.damage_taken));
+
+        combat.enemies[0].hp = 0;
+        assert!(combat.boss().is_none());
+    }
+
+    #[test]
+    fn stun_blocks_attacks_and_poison_kills() {
+        let maps = crate::data::parse_maps("@MAP:arena\n...\n@END\n");
+        let mut rng = Rng::new(11);
+        let mut player = Player::new("Hero".into(), "arena");
+        let mut spider = slime();
+        spider.on_hit = Some((
+            StatusEffect {
+                kind: StatusKind::Poison,
+                power: 1,
+                ticks: 300,
+            },
+            100,
+        ));
+
+        let mut combat = CombatSystem::new();
+        combat.spawn_enemy(&spider, 1, 0);
+        combat.enemies[0].status.apply(StatusEffect {
+            kind: StatusKind::Stun,
+            power: 0,
+            ticks: 5,
+        });
+        for _ in 0..5 {
+            assert_eq!(
+                combat
+                    .update(&mut player, &maps[0], &[], &mut rng)
+                    .damage_taken,
+                0
+            );
+        }
+
+        // 기절이 풀리면 물고, 독을 건다
+        let result = combat.update(&mut player, &maps[0], &[], &mut rng);
+        assert!(result.damage_taken > 0);
+        assert_eq!(player.status.power(StatusKind::Poison), 1);
+
+        combat.enemies[0].hp = 3;
+        combat.enemies[0].status.apply(StatusEffect {
+            kind: StatusKind::Poison,
+            power: 3,
+            ticks: 100,
+        });
+        let kills = (0..STATUS_PULSE)
+            .flat_map(|_| combat.update(&mut player, &maps[0], &[], &mut rng).kills)
+            .count();
+        assert_eq!(kills, 1);
+        assert!(combat.enemies.is_empty());
+    }
+}
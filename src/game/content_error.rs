@@ -0,0 +1,53 @@
+use alloc::format;
+use wipi::framebuffer::Framebuffer;
+
+use super::renderer::{
+    COLOR_BLACK, COLOR_GRAY, COLOR_RED, COLOR_WHITE, COLOR_YELLOW, clear_screen, draw_rect,
+    draw_text, fill_rect,
+};
+use super::state::ContentErrorState;
+use crate::data::ParseError;
+
+/// 데이터 파일 로딩 중 발생한 파싱 오류
+#[derive(Debug, Clone)]
+pub struct ContentError {
+    pub file: &'static str,
+    pub error: ParseError,
+}
+
+pub fn draw_content_errors(
+    fb: &mut Framebuffer,
+    errors: &[ContentError],
+    state: &ContentErrorState,
+) {
+    clear_screen(fb);
+
+    let screen_w = fb.width() as i32;
+    let screen_h = fb.height() as i32;
+
+    fill_rect(fb, 4, 4, screen_w - 8, screen_h - 8, COLOR_BLACK);
+    draw_rect(fb, 4, 4, screen_w - 8, screen_h - 8, COLOR_RED);
+
+    let title = format!("CONTENT ERROR ({})", errors.len());
+    draw_text(fb, 8, 6, &title, COLOR_RED);
+
+    let max_chars = ((screen_w - 16) / 6) as usize;
+    let mut y = 20;
+
+    for content_error in errors.iter().skip(state.scroll) {
+        if y > screen_h - 36 {
+            break;
+        }
+
+        let location = format!("{}:{}", content_error.file, content_error.error.line);
+        draw_text(fb, 8, y, &location, COLOR_YELLOW);
+
+        let reason: alloc::string::String =
+            content_error.error.reason.chars().take(max_chars).collect();
+        draw_text(fb, 12, y + 10, &reason, COLOR_WHITE);
+
+        y += 22;
+    }
+
+    draw_text(fb, 8, screen_h - 14, "Back:Exit", COLOR_GRAY);
+}
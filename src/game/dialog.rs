@@ -12,7 +12,17 @@ pub fn draw_dialog(fb: &mut Framebuffer, state: &DialogState) {
     let screen_w = fb.width() as i32;
     let screen_h = fb.height() as i32;
 
-    let box_h = 40;
+    let max_chars = ((screen_w - 16) / 6) as usize;
+    let mut lines = state
+        .current_text()
+        .map(|text| wrap_text(text, max_chars))
+        .unwrap_or_default();
+    lines.truncate(2);
+
+    // 선택지는 실제로 줄바꿈된 본문 줄 수만큼 아래에서 시작한다
+    let choices = state.choices();
+    let text_h = lines.len() as i32 * 10;
+    let box_h = 20 + text_h + choices.len() as i32 * 10;
     let box_y = screen_h - box_h - 4;
 
     fill_rect(fb, 4, box_y, screen_w - 8, box_h, COLOR_BLACK);
@@ -20,19 +30,28 @@ pub fn draw_dialog(fb: &mut Framebuffer, state: &DialogState) {
 
     draw_text(fb, 8, box_y + 2, &state.npc_name, COLOR_YELLOW);
 
-    if let Some(text) = state.current_text() {
-        let max_chars = ((screen_w - 16) / 6) as usize;
-        let lines = wrap_text(text, max_chars);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(fb, 8, box_y + 12 + (i as i32 * 10), line, COLOR_WHITE);
+    }
 
-        for (i, line) in lines.iter().take(2).enumerate() {
-            draw_text(fb, 8, box_y + 12 + (i as i32 * 10), line, COLOR_WHITE);
-        }
+    let choice_y = box_y + 12 + text_h;
+    for (i, (_, text)) in choices.iter().enumerate() {
+        let y = choice_y + i as i32 * 10;
+        let color = if i == state.selected_choice {
+            draw_text(fb, 10, y, ">", COLOR_YELLOW);
+            COLOR_WHITE
+        } else {
+            COLOR_GRAY
+        };
+        draw_text(fb, 18, y, text, color);
     }
 
-    let indicator = if state.current_line + 1 < state.lines.len() {
-        "OK:Next"
-    } else {
+    let indicator = if !choices.is_empty() {
+        "OK:Select"
+    } else if state.is_last_line() {
         "OK:Close"
+    } else {
+        "OK:Next"
     };
     draw_text(fb, screen_w - 50, box_y + box_h - 10, indicator, COLOR_GRAY);
 }
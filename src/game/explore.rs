@@ -2,13 +2,14 @@ use alloc::format;
 use wipi::framebuffer::{Color, Framebuffer};
 
 use super::Player;
-use super::combat::{CombatSystem, Direction};
+use super::combat::{CombatSystem, Direction, FieldEnemy};
 use super::renderer::{
     COLOR_BLACK, COLOR_BLUE, COLOR_BROWN, COLOR_CYAN, COLOR_DARK_GRAY, COLOR_DUNGEON, COLOR_FOREST,
     COLOR_GRAY, COLOR_GREEN, COLOR_RED, COLOR_WHITE, COLOR_YELLOW, TILE_SIZE, clear_screen,
     draw_hp_bar, draw_rect, draw_text, fill_rect,
 };
-use crate::data::{Map, Npc, Tile};
+use super::status::StatusEffects;
+use crate::data::{LevelUp, Map, MapLink, Npc, SkillEffect, StatusKind, Tile};
 
 pub fn draw_explore(
     fb: &mut Framebuffer,
@@ -21,6 +22,10 @@ pub fn draw_explore(
     let screen_h = fb.height() as i32;
     draw_map_with_entities(fb, map, player, combat, npcs, screen_h);
     draw_hud(fb, map, player, combat, screen_h);
+
+    if let Some(boss) = combat.boss() {
+        draw_boss_bar(fb, boss);
+    }
 }
 
 fn draw_map_with_entities(
@@ -52,14 +57,31 @@ fn draw_map_with_entities(
             if map_x < 0 || map_y < 0 || map_x >= map.width as i32 || map_y >= map.height as i32 {
                 fill_rect(fb, px, py, TILE_SIZE, TILE_SIZE, COLOR_BLACK);
             } else {
-                let tile = map.get_tile(map_x as usize, map_y as usize);
-                fill_rect(fb, px, py, TILE_SIZE, TILE_SIZE, tile_color(tile));
+                let (x, y) = (map_x as usize, map_y as usize);
+                fill_rect(
+                    fb,
+                    px,
+                    py,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                    tile_color(map.get_tile(x, y)),
+                );
+
+                // 닫힌 문은 바닥 위에 창살로 그린다
+                if let Some(conditions) = map.gate_conditions(x, y)
+                    && !player.check_conditions(conditions)
+                {
+                    for i in 0..3 {
+                        let bar_x = px + 1 + i * (TILE_SIZE - 2) / 3;
+                        fill_rect(fb, bar_x, py, 2, TILE_SIZE, COLOR_BROWN);
+                    }
+                }
             }
         }
     }
 
     for npc in npcs {
-        if npc.map_id != player.current_map_id {
+        if npc.map_id != player.current_map_id || !player.check_conditions(&npc.conditions) {
             continue;
         }
 
@@ -107,6 +129,40 @@ fn draw_map_with_entities(
                 TILE_SIZE - 2,
                 enemy_color,
             );
+
+            // 걸려 있는 상태 효과를 오른쪽 위 점으로 표시
+            if let Some(effect) = enemy.status.iter().next() {
+                let (_, color) = status_icon(effect.kind);
+                fill_rect(fb, px + TILE_SIZE - 3, py + 1, 2, 2, color);
+            }
+        }
+    }
+
+    if let Some(flash) = &combat.skill_flash {
+        let color = match flash.effect {
+            SkillEffect::Damage => COLOR_YELLOW,
+            SkillEffect::Heal => COLOR_GREEN,
+            SkillEffect::Buff => COLOR_CYAN,
+        };
+
+        for &(x, y) in &flash.tiles {
+            let screen_x = x as i32 - camera_x;
+            let screen_y = y as i32 - camera_y;
+
+            if screen_x >= 0
+                && screen_y >= 0
+                && screen_x < view_tiles_x as i32
+                && screen_y < view_tiles_y as i32
+            {
+                draw_rect(
+                    fb,
+                    screen_x * TILE_SIZE,
+                    screen_y * TILE_SIZE,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                    color,
+                );
+            }
         }
     }
 
@@ -171,14 +227,88 @@ fn draw_hud(
     let hp_text = format!("{}/{}", player.stats.current_hp, player.stats.max_hp);
     draw_text(fb, 46, hud_y + 10, &hp_text, COLOR_WHITE);
 
+    let mp_text = format!("MP{}", player.stats.current_mp);
+    draw_text(fb, screen_w - 66, hud_y + 2, &mp_text, COLOR_BLUE);
+
     let lv_text = format!("Lv{}", player.stats.level);
     draw_text(fb, screen_w - 30, hud_y + 2, &lv_text, COLOR_YELLOW);
 
+    if combat.critical_flash > 0 {
+        draw_text(fb, screen_w / 2 - 15, hud_y + 2, "CRIT!", COLOR_YELLOW);
+    } else {
+        draw_status_icons(fb, screen_w / 2 - 15, hud_y + 2, &player.status);
+    }
+
     if let Some(enemy) = combat.enemies.iter().find(|e| !e.is_dead()) {
         draw_text(fb, screen_w - 50, hud_y + 10, &enemy.data.name, COLOR_RED);
     }
 }
 
+/// 상태 효과마다 색 칸에 머리글자 하나
+fn draw_status_icons(fb: &mut Framebuffer, x: i32, y: i32, status: &StatusEffects) {
+    for (i, effect) in status.iter().enumerate() {
+        let (label, color) = status_icon(effect.kind);
+        let icon_x = x + i as i32 * 9;
+        fill_rect(fb, icon_x, y, 8, 8, color);
+        draw_text(fb, icon_x + 1, y, label, COLOR_BLACK);
+    }
+}
+
+fn status_icon(kind: StatusKind) -> (&'static str, Color) {
+    match kind {
+        StatusKind::Poison => ("P", COLOR_GREEN),
+        StatusKind::Regen => ("R", COLOR_CYAN),
+        StatusKind::Stun => ("S", COLOR_YELLOW),
+        StatusKind::AtkUp => ("A", COLOR_RED),
+        StatusKind::DefUp => ("D", COLOR_BLUE),
+    }
+}
+
+/// 알림 아래에 보스 이름, 단계, 체력을 보여준다
+fn draw_boss_bar(fb: &mut Framebuffer, boss: &FieldEnemy) {
+    let screen_w = fb.width() as i32;
+
+    fill_rect(fb, 4, 20, screen_w - 8, 18, COLOR_BLACK);
+    draw_rect(fb, 4, 20, screen_w - 8, 18, COLOR_RED);
+
+    let title = format!(
+        "{} ({}/{})",
+        boss.data.name,
+        boss.phase + 1,
+        boss.phases.len()
+    );
+    draw_text(fb, 8, 22, &title, COLOR_RED);
+    draw_hp_bar(fb, 8, 32, screen_w - 16, boss.hp, boss.data.hp);
+}
+
+pub fn draw_notice(fb: &mut Framebuffer, text: &str) {
+    let screen_w = fb.width() as i32;
+    let w = (text.chars().count() as i32 * 6 + 8).min(screen_w - 8);
+    let x = (screen_w - w) / 2;
+
+    fill_rect(fb, x, 4, w, 14, COLOR_BLACK);
+    draw_rect(fb, x, 4, w, 14, COLOR_YELLOW);
+    draw_text(fb, x + 4, 6, text, COLOR_WHITE);
+}
+
+/// 오른 레벨과 늘어난 스탯을 화면 가운데 위쪽에 보여준다
+pub fn draw_level_up(fb: &mut Framebuffer, up: &LevelUp) {
+    let screen_w = fb.width() as i32;
+    let (w, h) = (110, 36);
+    let x = (screen_w - w) / 2;
+    let y = 24;
+
+    fill_rect(fb, x, y, w, h, COLOR_BLACK);
+    draw_rect(fb, x, y, w, h, COLOR_YELLOW);
+
+    let title = format!("LEVEL UP! Lv{}>{}", up.from, up.to);
+    draw_text(fb, x + 4, y + 2, &title, COLOR_YELLOW);
+    let hp_mp = format!("HP+{} MP+{}", up.hp, up.mp);
+    draw_text(fb, x + 4, y + 12, &hp_mp, COLOR_WHITE);
+    let atk_def = format!("ATK+{} DEF+{}", up.atk, up.def);
+    draw_text(fb, x + 4, y + 22, &atk_def, COLOR_WHITE);
+}
+
 fn tile_color(tile: Tile) -> Color {
     match tile {
         Tile::Wall => COLOR_DARK_GRAY,
@@ -197,23 +327,17 @@ pub fn check_tile_event(map: &Map, player: &Player) -> Option<TileEvent> {
 
     match tile {
         Tile::Treasure => Some(TileEvent::Treasure),
-        Tile::Exit => {
-            for (ex, ey, target) in &map.exits {
-                if *ex == player.x && *ey == player.y {
-                    return Some(TileEvent::MapExit(target.clone()));
-                }
-            }
-            None
-        }
+        Tile::Exit => map
+            .exits
+            .iter()
+            .find(|link| link.x == player.x && link.y == player.y)
+            .map(|link| TileEvent::MapExit(link.clone())),
         Tile::House => Some(TileEvent::Npc),
-        Tile::Dungeon => {
-            for (dx, dy, target) in &map.dungeons {
-                if *dx == player.x && *dy == player.y {
-                    return Some(TileEvent::DungeonEntrance(target.clone()));
-                }
-            }
-            None
-        }
+        Tile::Dungeon => map
+            .dungeons
+            .iter()
+            .find(|link| link.x == player.x && link.y == player.y)
+            .map(|link| TileEvent::DungeonEntrance(link.clone())),
         _ => None,
     }
 }
@@ -221,7 +345,7 @@ pub fn check_tile_event(map: &Map, player: &Player) -> Option<TileEvent> {
 #[derive(Debug, Clone)]
 pub enum TileEvent {
     Treasure,
-    MapExit(alloc::string::String),
+    MapExit(MapLink),
     Npc,
-    DungeonEntrance(alloc::string::String),
+    DungeonEntrance(MapLink),
 }
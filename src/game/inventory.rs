@@ -1,7 +1,11 @@
 use alloc::format;
-use wipi::framebuffer::Framebuffer;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use wipi::framebuffer::{Color, Framebuffer};
 
 use super::Player;
+use super::player::InventoryEntry;
 use super::renderer::{
     COLOR_BLACK, COLOR_BLUE, COLOR_DARK_GRAY, COLOR_GRAY, COLOR_GREEN, COLOR_RED, COLOR_WHITE,
     COLOR_YELLOW, clear_screen, draw_hp_bar, draw_rect, draw_selection_cursor, draw_text,
@@ -9,13 +13,91 @@ use super::renderer::{
 };
 use crate::data::ItemKind;
 
+/// 한 화면에 보이는 아이템 줄 수
+const VISIBLE_ITEMS: usize = 6;
+
+/// 목록 위의 분류 탭. None은 전체
+const TABS: [Option<ItemKind>; 6] = [
+    None,
+    Some(ItemKind::Weapon),
+    Some(ItemKind::Armor),
+    Some(ItemKind::Accessory),
+    Some(ItemKind::Consumable),
+    Some(ItemKind::Quest),
+];
+
+/// 선택한 아이템에 할 수 있는 일
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemAction {
+    Use,
+    Equip,
+    Unequip,
+    Drop,
+}
+
+impl ItemAction {
+    fn label(self) -> &'static str {
+        match self {
+            ItemAction::Use => "Use",
+            ItemAction::Equip => "Equip",
+            ItemAction::Unequip => "Unequip",
+            ItemAction::Drop => "Drop",
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct InventoryState {
     pub selected: usize,
     pub scroll: usize,
+    pub tab: usize,
+    pub menu: Option<usize>, // 열려 있는 행동 메뉴에서 고른 줄
 }
 
 impl InventoryState {
+    /// 현재 탭에 보이는 칸들
+    pub fn entries<'a>(&self, player: &'a Player) -> Vec<&'a InventoryEntry> {
+        player
+            .inventory()
+            .iter()
+            .filter(|e| TABS[self.tab].is_none_or(|kind| e.item.kind == kind))
+            .collect()
+    }
+
+    pub fn selected_uid(&self, player: &Player) -> Option<u32> {
+        self.entries(player).get(self.selected).map(|e| e.uid)
+    }
+
+    /// 선택한 아이템의 행동 메뉴. 퀘스트 아이템은 버릴 수 없다
+    pub fn actions(&self, player: &Player) -> Vec<ItemAction> {
+        let Some(entry) = self.entries(player).get(self.selected).copied() else {
+            return Vec::new();
+        };
+        match entry.item.kind {
+            ItemKind::Consumable => vec![ItemAction::Use, ItemAction::Drop],
+            ItemKind::Quest => Vec::new(),
+            _ if player.is_equipped(entry.uid) => vec![ItemAction::Unequip, ItemAction::Drop],
+            _ => vec![ItemAction::Equip, ItemAction::Drop],
+        }
+    }
+
+    pub fn selected_action(&self, player: &Player) -> Option<ItemAction> {
+        self.menu
+            .and_then(|index| self.actions(player).get(index).copied())
+    }
+
+    pub fn next_tab(&mut self) {
+        self.tab = (self.tab + 1) % TABS.len();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.tab = (self.tab + TABS.len() - 1) % TABS.len();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
     pub fn move_up(&mut self) {
         if self.selected > 0 {
             self.selected -= 1;
@@ -28,11 +110,27 @@ impl InventoryState {
     pub fn move_down(&mut self, item_count: usize) {
         if item_count > 0 && self.selected < item_count - 1 {
             self.selected += 1;
-            if self.selected >= self.scroll + 6 {
-                self.scroll = self.selected - 5;
+            if self.selected >= self.scroll + VISIBLE_ITEMS {
+                self.scroll = self.selected + 1 - VISIBLE_ITEMS;
             }
         }
     }
+
+    /// 아이템을 쓰거나 버려 목록이 줄었을 때 선택을 목록 안으로 되돌린다
+    pub fn clamp(&mut self, item_count: usize) {
+        self.selected = self.selected.min(item_count.saturating_sub(1));
+        self.scroll = self.scroll.min(self.selected);
+    }
+}
+
+fn kind_color(kind: ItemKind) -> Color {
+    match kind {
+        ItemKind::Weapon => COLOR_RED,
+        ItemKind::Armor => COLOR_BLUE,
+        ItemKind::Accessory => COLOR_YELLOW,
+        ItemKind::Consumable => COLOR_GREEN,
+        ItemKind::Quest => COLOR_GRAY,
+    }
 }
 
 pub fn draw_inventory(fb: &mut Framebuffer, player: &Player, state: &InventoryState) {
@@ -45,30 +143,29 @@ pub fn draw_inventory(fb: &mut Framebuffer, player: &Player, state: &InventorySt
     draw_rect(fb, 4, 4, screen_w - 8, screen_h - 8, COLOR_WHITE);
 
     draw_text(fb, 8, 8, "INVENTORY", COLOR_YELLOW);
+    draw_tabs(fb, screen_w - 8 - TABS.len() as i32 * 12, 7, state.tab);
 
-    if player.inventory.is_empty() {
+    let entries = state.entries(player);
+    if entries.is_empty() {
         draw_text(fb, 30, 60, "No items", COLOR_GRAY);
-        draw_text(fb, 8, screen_h - 16, "Back:Return", COLOR_GRAY);
+        draw_text(fb, 8, screen_h - 16, "L/R:Tab Back:Return", COLOR_GRAY);
         return;
     }
 
-    let visible_items: i32 = 6;
     let start_y: i32 = 24;
     let item_height: i32 = 14;
 
-    for (i, item) in player
-        .inventory
+    for (i, entry) in entries
         .iter()
         .skip(state.scroll)
-        .take(visible_items as usize)
+        .take(VISIBLE_ITEMS)
         .enumerate()
     {
         let actual_idx = state.scroll + i;
         let y = start_y + (i as i32) * item_height;
 
-        let is_equipped = player.equipped_weapon == Some(actual_idx)
-            || player.equipped_armor == Some(actual_idx)
-            || player.equipped_accessory == Some(actual_idx);
+        let item = &entry.item;
+        let is_equipped = player.is_equipped(entry.uid);
 
         if actual_idx == state.selected {
             draw_selection_cursor(fb, 8, y);
@@ -89,35 +186,124 @@ pub fn draw_inventory(fb: &mut Framebuffer, player: &Player, state: &InventorySt
             },
         );
 
-        let type_indicator = match item.kind {
-            ItemKind::Weapon => COLOR_RED,
-            ItemKind::Armor => COLOR_BLUE,
-            ItemKind::Accessory => COLOR_YELLOW,
-            ItemKind::Consumable => COLOR_GREEN,
-        };
-        fill_rect(fb, 18, y + 2, 4, 8, type_indicator);
+        fill_rect(fb, 18, y + 2, 4, 8, kind_color(item.kind));
 
         let equip_mark = if is_equipped { "E " } else { "  " };
-        let item_text = format!("{}{}", equip_mark, item.name);
+        let item_text = if entry.count > 1 {
+            format!("{}{} x{}", equip_mark, item.name, entry.count)
+        } else {
+            format!("{}{}", equip_mark, item.name)
+        };
         draw_text(fb, 24, y + 2, &item_text, COLOR_WHITE);
     }
 
-    if player.inventory.len() > visible_items as usize {
+    if entries.len() > VISIBLE_ITEMS {
         if state.scroll > 0 {
             draw_text(fb, screen_w - 16, 24, "^", COLOR_WHITE);
         }
-        if state.scroll + (visible_items as usize) < player.inventory.len() {
+        if state.scroll + VISIBLE_ITEMS < entries.len() {
             draw_text(
                 fb,
                 screen_w - 16,
-                start_y + visible_items * item_height - 8,
+                start_y + VISIBLE_ITEMS as i32 * item_height - 8,
                 "v",
                 COLOR_WHITE,
             );
         }
     }
 
-    draw_text(fb, 8, screen_h - 16, "OK:Use Back:Return", COLOR_GRAY);
+    if let Some(entry) = entries.get(state.selected) {
+        let detail_y = start_y + VISIBLE_ITEMS as i32 * item_height + 4;
+        draw_item_detail(fb, player, entry, detail_y);
+
+        if let Some(selected) = state.menu {
+            let menu_y = start_y + (state.selected - state.scroll) as i32 * item_height + 12;
+            draw_action_menu(fb, &state.actions(player), selected, screen_w - 60, menu_y);
+        }
+    }
+
+    let help = if state.menu.is_some() {
+        "OK:Select Back:Cancel"
+    } else {
+        "OK:Menu L/R:Tab Back:Return"
+    };
+    draw_text(fb, 8, screen_h - 16, help, COLOR_GRAY);
+}
+
+/// 탭마다 분류 색 칸 하나. 전체 탭은 흰 칸
+fn draw_tabs(fb: &mut Framebuffer, x: i32, y: i32, selected: usize) {
+    for (i, tab) in TABS.iter().enumerate() {
+        let tab_x = x + i as i32 * 12;
+        let color = tab.map_or(COLOR_WHITE, kind_color);
+        if i == selected {
+            fill_rect(fb, tab_x, y, 10, 10, color);
+        } else {
+            fill_rect(fb, tab_x + 3, y + 3, 4, 4, color);
+        }
+        draw_rect(fb, tab_x, y, 10, 10, COLOR_GRAY);
+    }
+}
+
+/// 선택한 아이템의 능력치, 판매가, 지금 장비와 비교한 공격력/방어력 변화
+fn draw_item_detail(fb: &mut Framebuffer, player: &Player, entry: &InventoryEntry, y: i32) {
+    let screen_w = fb.width() as i32;
+    let item = &entry.item;
+
+    fill_rect(fb, 8, y, screen_w - 16, 32, COLOR_BLACK);
+    draw_rect(fb, 8, y, screen_w - 16, 32, kind_color(item.kind));
+
+    let stats = match item.kind {
+        ItemKind::Weapon => format!("ATK {} CRIT {}%", item.param1, item.param2),
+        ItemKind::Armor => format!("DEF {}", item.param1),
+        ItemKind::Accessory => format!("ATK {} DEF {}", item.param1, item.param2),
+        ItemKind::Consumable => format!("HP +{}", item.param1),
+        ItemKind::Quest => String::from("Quest item"),
+    };
+    draw_text(fb, 12, y + 2, &stats, COLOR_WHITE);
+
+    // 실제로 받는 값은 상점마다 매입 비율이 다르다
+    let price_text = format!("Base {}G", item.price);
+    draw_text(fb, screen_w - 60, y + 2, &price_text, COLOR_YELLOW);
+
+    if let Some((atk, def)) = player.equip_delta(entry.uid) {
+        draw_text(fb, 12, y + 14, "ATK", COLOR_WHITE);
+        draw_text(fb, 32, y + 14, &signed(atk), delta_color(atk));
+        draw_text(fb, 62, y + 14, "DEF", COLOR_WHITE);
+        draw_text(fb, 82, y + 14, &signed(def), delta_color(def));
+    }
+}
+
+fn signed(value: i32) -> String {
+    if value > 0 {
+        format!("+{}", value)
+    } else {
+        format!("{}", value)
+    }
+}
+
+fn delta_color(value: i32) -> Color {
+    match value.cmp(&0) {
+        core::cmp::Ordering::Greater => COLOR_GREEN,
+        core::cmp::Ordering::Less => COLOR_RED,
+        core::cmp::Ordering::Equal => COLOR_GRAY,
+    }
+}
+
+fn draw_action_menu(fb: &mut Framebuffer, actions: &[ItemAction], selected: usize, x: i32, y: i32) {
+    let h = actions.len() as i32 * 10 + 4;
+    fill_rect(fb, x, y, 52, h, COLOR_BLACK);
+    draw_rect(fb, x, y, 52, h, COLOR_WHITE);
+
+    for (i, action) in actions.iter().enumerate() {
+        let line_y = y + 2 + i as i32 * 10;
+        let color = if i == selected {
+            draw_text(fb, x + 2, line_y, ">", COLOR_YELLOW);
+            COLOR_WHITE
+        } else {
+            COLOR_GRAY
+        };
+        draw_text(fb, x + 10, line_y, action.label(), color);
+    }
 }
 
 pub fn draw_stats(fb: &mut Framebuffer, player: &Player) {
@@ -158,18 +344,35 @@ pub fn draw_stats(fb: &mut Framebuffer, player: &Player) {
     let mp_text = format!("{}/{}", player.stats.current_mp, player.stats.max_mp);
     draw_text(fb, 94, stat_y + line_height, &mp_text, COLOR_WHITE);
 
-    let stats = [
-        ("LV", player.stats.level),
-        ("ATK", player.total_atk()),
-        ("DEF", player.total_def()),
-        ("EXP", player.stats.exp),
-        ("GOLD", player.stats.gold),
+    let equipment = player.equipment_stats();
+    let lines = [
+        format!("LV: {}", player.stats.level),
+        format!(
+            "ATK: {} ({}+{}+{})",
+            player.total_atk(),
+            player.stats.base_atk,
+            equipment.weapon_atk,
+            equipment.accessory_atk
+        ),
+        format!(
+            "DEF: {} ({}+{}+{})",
+            player.total_def(),
+            player.stats.base_def,
+            equipment.armor_def,
+            equipment.accessory_def
+        ),
+        format!("CRIT: {}%", equipment.crit),
+        if player.stats.exp_to_next > 0 {
+            format!("EXP: {}/{}", player.stats.exp, player.stats.exp_to_next)
+        } else {
+            String::from("EXP: MAX")
+        },
+        format!("GOLD: {}", player.stats.gold),
     ];
 
-    for (i, (label, value)) in stats.iter().enumerate() {
+    for (i, text) in lines.iter().enumerate() {
         let y = stat_y + ((i + 2) as i32) * line_height;
-        let text = format!("{}: {}", label, value);
-        draw_text(fb, 10, y, &text, COLOR_WHITE);
+        draw_text(fb, 10, y, text, COLOR_WHITE);
     }
 
     draw_text(fb, 8, screen_h - 16, "Back:Return", COLOR_GRAY);
@@ -19,9 +19,9 @@ pub fn draw_menu(fb: &mut Framebuffer, state: &MenuState) {
     let menu_spacing: i32 = 18;
 
     let items: &[&str] = if state.has_save {
-        &["NEW GAME", "CONTINUE", "EXIT"]
+        &["NEW GAME", "HARDCORE", "CONTINUE", "EXIT"]
     } else {
-        &["NEW GAME", "EXIT"]
+        &["NEW GAME", "HARDCORE", "EXIT"]
     };
 
     for (i, item) in items.iter().enumerate() {
@@ -0,0 +1,144 @@
+use crate::data::Map;
+
+/// next_step이 찾아보는 최대 거리
+pub const MAX_SEARCH_DIST: usize = 16;
+/// 탐색 창의 한 변. from을 가운데 두고 MAX_SEARCH_DIST 안의 칸만 담는다
+const WINDOW: usize = 2 * MAX_SEARCH_DIST + 1;
+
+/// from에서 to까지 가는 최단 경로(BFS)의 첫 칸.
+/// from에서 맨해튼 거리 max_dist(최대 MAX_SEARCH_DIST) 안의 칸만 찾아보고, 길이 없으면 None.
+/// to 자체는 can_enter와 상관없이 도착할 수 있는 칸으로 본다.
+/// 적마다 매 이동 틱 부르므로 힙을 쓰지 않고 창 크기의 고정 버퍼만 쓴다.
+pub fn next_step(
+    map: &Map,
+    from: (usize, usize),
+    to: (usize, usize),
+    max_dist: usize,
+    can_enter: impl Fn(usize, usize) -> bool,
+) -> Option<(usize, usize)> {
+    let max_dist = max_dist.min(MAX_SEARCH_DIST);
+    if from == to || from.0 >= map.width || from.1 >= map.height {
+        return None;
+    }
+
+    // max_dist 안의 칸만 넣으므로 창을 벗어나지 않는다
+    let slot = |(x, y): (usize, usize)| {
+        (y + MAX_SEARCH_DIST - from.1) * WINDOW + (x + MAX_SEARCH_DIST - from.0)
+    };
+    // 각 칸에 처음 도착할 때 움직인 방향 (neighbours 순번 + 1). 0은 아직 가 보지 않은 칸
+    let mut came_by = [0u8; WINDOW * WINDOW];
+    let mut queue = [(0u8, 0u8); WINDOW * WINDOW];
+    let to_window = |(x, y): (usize, usize)| {
+        (
+            (x + MAX_SEARCH_DIST - from.0) as u8,
+            (y + MAX_SEARCH_DIST - from.1) as u8,
+        )
+    };
+    came_by[slot(from)] = u8::MAX;
+    queue[0] = to_window(from);
+    let (mut head, mut tail) = (0, 1);
+
+    while head < tail {
+        let (wx, wy) = queue[head];
+        head += 1;
+        let pos = (
+            wx as usize + from.0 - MAX_SEARCH_DIST,
+            wy as usize + from.1 - MAX_SEARCH_DIST,
+        );
+
+        if pos == to {
+            let mut step = pos;
+            loop {
+                let prev = step_back(step, came_by[slot(step)]);
+                if prev == from {
+                    return Some(step);
+                }
+                step = prev;
+            }
+        }
+
+        for (dir, next) in neighbours(pos).into_iter().enumerate() {
+            if next.0 >= map.width
+                || next.1 >= map.height
+                || next.0.abs_diff(from.0) + next.1.abs_diff(from.1) > max_dist
+                || came_by[slot(next)] != 0
+                || (next != to && !can_enter(next.0, next.1))
+            {
+                continue;
+            }
+            came_by[slot(next)] = dir as u8 + 1;
+            queue[tail] = to_window(next);
+            tail += 1;
+        }
+    }
+
+    None
+}
+
+/// came_by 방향으로 들어온 칸의 바로 앞 칸
+fn step_back((x, y): (usize, usize), came_by: u8) -> (usize, usize) {
+    match came_by {
+        1 => (x, y + 1),
+        2 => (x, y - 1),
+        3 => (x + 1, y),
+        _ => (x - 1, y),
+    }
+}
+
+/// from과 to 사이를 잇는 직선(브레젠험)이 지나는 칸이 모두 지나갈 수 있는 칸인지. 양 끝은 보지 않는다
+pub fn clear_line(map: &Map, from: (usize, usize), to: (usize, usize)) -> bool {
+    let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+    let (tx, ty) = (to.0 as i32, to.1 as i32);
+    let (dx, dy) = ((tx - x).abs(), -(ty - y).abs());
+    let (sx, sy) = ((tx - x).signum(), (ty - y).signum());
+    let mut err = dx + dy;
+
+    loop {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+        if (x, y) == (tx, ty) {
+            return true;
+        }
+        if !map.get_tile(x as usize, y as usize).is_passable() {
+            return false;
+        }
+    }
+}
+
+/// 상하좌우 이웃 칸. 0보다 작아지는 칸은 맵 밖 좌표로 바꿔 걸러지게 한다
+pub fn neighbours((x, y): (usize, usize)) -> [(usize, usize); 4] {
+    [
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_around_walls() {
+        let map = crate::data::parse_maps("@MAP:m\n.#.\n.#.\n...\n@END\n").remove(0);
+        let open = |x, y| map.get_tile(x, y).is_passable();
+
+        // 벽을 돌아 아래로 내려간다
+        assert_eq!(next_step(&map, (0, 0), (2, 0), 10, open), Some((0, 1)));
+        // 탐색 거리가 모자라면 길을 찾지 못한다
+        assert_eq!(next_step(&map, (0, 0), (2, 0), 2, open), None);
+
+        // 벽 너머로는 보이지 않는다
+        assert!(!clear_line(&map, (0, 0), (2, 0)));
+        assert!(clear_line(&map, (0, 2), (2, 2)));
+        assert!(clear_line(&map, (0, 0), (0, 2)));
+    }
+}
@@ -2,21 +2,51 @@ use alloc::string::String;
 use alloc::vec::Vec;
 
 use super::combat::Direction;
-use crate::data::{Item, ItemKind, Map, PlayerStats, QuestProgress};
+use super::status::StatusEffects;
+use crate::data::{
+    DialogCondition, EquipmentStats, Item, ItemKind, Map, PlayerStats, QuestProgress, Skill,
+    StatusKind,
+};
+
+/// 쓰러졌을 때 잃는 골드 비율(%)
+pub const DEATH_GOLD_PENALTY: i32 = 10;
+
+/// 스킬 단축키 수 (4~9번 키)
+pub const SKILL_KEY_COUNT: usize = crate::data::MAX_SKILLS;
+
+/// 인벤토리의 한 칸. uid는 한 플레이어 안에서 다시 쓰이지 않는다.
+/// 겹쳐지는 아이템은 count개가 한 칸을 쓰고, 장비는 항상 1개다.
+#[derive(Debug, Clone)]
+pub struct InventoryEntry {
+    pub uid: u32,
+    pub item: Item,
+    pub count: u32,
+}
 
 pub struct Player {
     pub name: String,
     pub stats: PlayerStats,
-    pub inventory: Vec<Item>,
-    pub equipped_weapon: Option<usize>,
-    pub equipped_armor: Option<usize>,
-    pub equipped_accessory: Option<usize>,
+    // 인벤토리와 장비는 아래 메서드로만 바꿔서 장비가 항상 인벤토리 안의 아이템을 가리키게 한다
+    inventory: Vec<InventoryEntry>,
+    next_uid: u32,
+    equipped_weapon: Option<u32>,
+    equipped_armor: Option<u32>,
+    equipped_accessory: Option<u32>,
     pub current_map_id: String,
     pub x: usize,
     pub y: usize,
     pub facing: Direction,
     pub quests: Vec<QuestProgress>,
     pub opened_treasures: Vec<(String, usize, usize)>, // (map_id, x, y)
+    pub defeated_bosses: Vec<String>,                  // 쓰러뜨린 보스 enemy_id
+    pub defeated_patrols: Vec<(String, usize)>,        // 쓰러뜨린 순찰 적 (map_id, @PATROL 순번)
+    pub fired_triggers: Vec<(String, usize)>, // 발동한 ONCE 트리거 (map_id, 맵 안의 트리거 순번)
+    pub vars: Vec<(String, i32)>,             // 이야기 진행 변수. 플래그는 0이 아닌 값
+    pub status: StatusEffects,                // 저장하지 않는다
+    pub respawn: Option<(String, usize, usize)>, // 쓰러지면 돌아오는 곳 (map_id, x, y)
+    pub deaths: u32,
+    pub hardcore: bool, // 쓰러지면 되살아나지 않고 게임이 끝난다
+    pub play_ticks: u32,
 }
 
 impl Player {
@@ -25,6 +55,7 @@ impl Player {
             name,
             stats: PlayerStats::default(),
             inventory: Vec::new(),
+            next_uid: 1,
             equipped_weapon: None,
             equipped_armor: None,
             equipped_accessory: None,
@@ -34,6 +65,15 @@ impl Player {
             facing: Direction::Down,
             quests: Vec::new(),
             opened_treasures: Vec::new(),
+            defeated_bosses: Vec::new(),
+            defeated_patrols: Vec::new(),
+            fired_triggers: Vec::new(),
+            vars: Vec::new(),
+            status: StatusEffects::default(),
+            respawn: None,
+            deaths: 0,
+            hardcore: false,
+            play_ticks: 0,
         }
     }
 
@@ -49,84 +89,329 @@ impl Player {
         }
     }
 
-    pub fn spawn_at_map(&mut self, map: &Map) {
-        if let Some((x, y)) = map.find_player_start() {
+    pub fn is_boss_defeated(&self, enemy_id: &str) -> bool {
+        self.defeated_bosses.iter().any(|id| id == enemy_id)
+    }
+
+    pub fn defeat_boss(&mut self, enemy_id: &str) {
+        if !self.is_boss_defeated(enemy_id) {
+            self.defeated_bosses.push(enemy_id.into());
+        }
+    }
+
+    pub fn is_patrol_defeated(&self, map_id: &str, index: usize) -> bool {
+        self.defeated_patrols
+            .iter()
+            .any(|(m, i)| m == map_id && *i == index)
+    }
+
+    pub fn defeat_patrol(&mut self, map_id: &str, index: usize) {
+        if !self.is_patrol_defeated(map_id, index) {
+            self.defeated_patrols.push((map_id.into(), index));
+        }
+    }
+
+    pub fn is_trigger_fired(&self, map_id: &str, index: usize) -> bool {
+        self.fired_triggers
+            .iter()
+            .any(|(m, i)| m == map_id && *i == index)
+    }
+
+    pub fn mark_trigger_fired(&mut self, map_id: &str, index: usize) {
+        if !self.is_trigger_fired(map_id, index) {
+            self.fired_triggers.push((map_id.into(), index));
+        }
+    }
+
+    /// 설정한 적 없는 변수는 0
+    pub fn var(&self, name: &str) -> i32 {
+        self.vars
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| *value)
+            .unwrap_or(0)
+    }
+
+    pub fn set_var(&mut self, name: &str, value: i32) {
+        match self.vars.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.vars.push((name.into(), value)),
+        }
+    }
+
+    pub fn add_var(&mut self, name: &str, amount: i32) {
+        self.set_var(name, self.var(name).saturating_add(amount));
+    }
+
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.var(name) != 0
+    }
+
+    /// 이름 붙은 도착 지점에 선다. 없으면 맵의 P 타일
+    pub fn spawn_at_map(&mut self, map: &Map, spawn: Option<&str>) {
+        let point = spawn
+            .and_then(|name| map.spawn_point(name))
+            .or_else(|| map.find_player_start());
+        if let Some((x, y)) = point {
             self.x = x;
             self.y = y;
         }
         self.current_map_id = map.id.clone();
     }
 
+    /// 지금 서 있는 곳을 되살아날 곳으로 삼는다
+    pub fn set_respawn_here(&mut self) {
+        self.respawn = Some((self.current_map_id.clone(), self.x, self.y));
+    }
+
+    /// 여관 숙박비 (REST=price는 레벨 x price)
+    pub fn rest_price(&self, price: i32) -> i32 {
+        price * self.stats.level
+    }
+
+    /// 숙박비를 낸다. 골드가 모자라면 아무것도 내지 않고 false
+    pub fn pay_for_rest(&mut self, price: i32) -> bool {
+        let cost = self.rest_price(price);
+        if self.stats.gold < cost {
+            return false;
+        }
+        self.stats.gold -= cost;
+        true
+    }
+
+    /// 쓰러진 횟수를 세고, 하드코어가 아니면 골드 벌칙을 물린 뒤 회복시킨다.
+    /// 잃은 골드를 돌려주고, 하드코어라 판이 끝났으면 None
+    pub fn fall(&mut self) -> Option<i32> {
+        self.deaths += 1;
+        if self.hardcore {
+            return None;
+        }
+
+        let lost = self.stats.gold * DEATH_GOLD_PENALTY / 100;
+        self.stats.gold -= lost;
+        self.stats.current_hp = self.stats.max_hp;
+        self.stats.current_mp = self.stats.max_mp;
+        self.status.clear();
+        Some(lost)
+    }
+
+    /// 되살아날 맵과 좌표. 정해 둔 곳이 없거나 사라진 맵이면 첫 마을의 시작 지점
+    pub fn respawn_target<'a>(&self, maps: &'a [Map]) -> Option<(&'a Map, Option<(usize, usize)>)> {
+        self.respawn
+            .as_ref()
+            .and_then(|(map_id, x, y)| {
+                let map = maps.iter().find(|m| m.id == *map_id)?;
+                Some((map, Some((*x, *y))))
+            })
+            .or_else(|| maps.iter().find(|m| m.town).map(|m| (m, None)))
+    }
+
     pub fn get_weapon(&self) -> Option<&Item> {
-        self.equipped_weapon.and_then(|i| self.inventory.get(i))
+        self.equipped_weapon.and_then(|uid| self.get_item(uid))
     }
 
     pub fn get_armor(&self) -> Option<&Item> {
-        self.equipped_armor.and_then(|i| self.inventory.get(i))
+        self.equipped_armor.and_then(|uid| self.get_item(uid))
+    }
+
+    pub fn get_accessory(&self) -> Option<&Item> {
+        self.equipped_accessory.and_then(|uid| self.get_item(uid))
+    }
+
+    pub fn equipment_stats(&self) -> EquipmentStats {
+        let mut stats = EquipmentStats::default();
+        for item in [self.get_weapon(), self.get_armor(), self.get_accessory()]
+            .into_iter()
+            .flatten()
+        {
+            stats.add_item(item);
+        }
+        stats
     }
 
     pub fn total_atk(&self) -> i32 {
-        self.stats.total_atk(self.get_weapon())
+        self.stats.total_atk(&self.equipment_stats())
     }
 
     pub fn total_def(&self) -> i32 {
-        self.stats.total_def(self.get_armor())
+        self.stats.total_def(&self.equipment_stats())
     }
 
-    pub fn add_item(&mut self, item: Item) {
-        self.inventory.push(item);
+    /// 장비에 상태 효과까지 더한 전투용 공격력
+    pub fn combat_atk(&self) -> i32 {
+        self.total_atk() + self.status.power(StatusKind::AtkUp)
     }
 
-    pub fn use_item(&mut self, index: usize) -> bool {
-        if index >= self.inventory.len() {
-            return false;
-        }
+    pub fn combat_def(&self) -> i32 {
+        self.total_def() + self.status.power(StatusKind::DefUp)
+    }
 
-        let item = &self.inventory[index];
-        match item.kind {
-            ItemKind::Consumable => {
-                let heal = item.param1;
-                self.stats.heal(heal);
-                self.inventory.remove(index);
-                self.fix_equipped_indices(index);
-                true
-            }
-            ItemKind::Weapon => {
-                self.equipped_weapon = Some(index);
-                true
-            }
-            ItemKind::Armor => {
-                self.equipped_armor = Some(index);
-                true
+    pub fn crit_chance(&self) -> i32 {
+        self.equipment_stats().crit
+    }
+
+    /// 현재 레벨에서 배운 스킬. 배우는 레벨 순으로 4~9번 키에 배정되므로
+    /// 새로 배운 스킬은 항상 뒤에 붙는다.
+    pub fn known_skills<'a>(&self, skills: &'a [Skill]) -> Vec<&'a Skill> {
+        let mut known: Vec<_> = skills
+            .iter()
+            .filter(|s| s.level <= self.stats.level)
+            .collect();
+        known.sort_by_key(|s| s.level);
+        known.truncate(SKILL_KEY_COUNT);
+        known
+    }
+
+    pub fn inventory(&self) -> &[InventoryEntry] {
+        &self.inventory
+    }
+
+    pub fn get_item(&self, uid: u32) -> Option<&Item> {
+        self.inventory
+            .iter()
+            .find(|e| e.uid == uid)
+            .map(|e| &e.item)
+    }
+
+    /// 목록 화면의 위치로 uid를 찾는다
+    pub fn uid_at(&self, index: usize) -> Option<u32> {
+        self.inventory.get(index).map(|e| e.uid)
+    }
+
+    pub fn is_equipped(&self, uid: u32) -> bool {
+        [
+            self.equipped_weapon,
+            self.equipped_armor,
+            self.equipped_accessory,
+        ]
+        .contains(&Some(uid))
+    }
+
+    pub fn add_item(&mut self, item: Item) -> u32 {
+        self.add_items(item, 1)
+    }
+
+    /// 같은 아이템이 있는 칸부터 최대 겹침 수까지 채우고, 남으면 새 칸을 만든다.
+    /// 마지막으로 채운 칸의 uid를 돌려준다.
+    pub fn add_items(&mut self, item: Item, mut count: u32) -> u32 {
+        let mut last_uid = 0;
+
+        for entry in &mut self.inventory {
+            if count == 0 {
+                break;
             }
-            ItemKind::Accessory => {
-                self.equipped_accessory = Some(index);
-                true
+            if entry.item.id == item.id && entry.count < item.max_stack {
+                let added = count.min(item.max_stack - entry.count);
+                entry.count += added;
+                count -= added;
+                last_uid = entry.uid;
             }
         }
+
+        while count > 0 {
+            let added = count.min(item.max_stack.max(1));
+            let uid = self.next_uid;
+            self.next_uid += 1;
+            self.inventory.push(InventoryEntry {
+                uid,
+                item: item.clone(),
+                count: added,
+            });
+            count -= added;
+            last_uid = uid;
+        }
+
+        last_uid
     }
 
-    fn fix_equipped_indices(&mut self, removed: usize) {
-        if let Some(ref mut i) = self.equipped_weapon {
-            if *i > removed {
-                *i -= 1;
-            } else if *i == removed {
-                self.equipped_weapon = None;
-            }
+    /// 칸에서 아이템 하나를 꺼낸다. 칸이 비면 사라지고, 장비 중이었다면 장비도 해제된다.
+    pub fn take_item(&mut self, uid: u32) -> Option<Item> {
+        self.take_items(uid, 1).map(|(item, _)| item)
+    }
+
+    /// uid 칸에서 최대 count개를 꺼낸다. 실제로 꺼낸 수를 함께 돌려준다
+    pub fn take_items(&mut self, uid: u32, count: u32) -> Option<(Item, u32)> {
+        let index = self.inventory.iter().position(|e| e.uid == uid)?;
+        let entry = &mut self.inventory[index];
+        let taken = count.min(entry.count);
+        if taken == 0 {
+            return None;
+        }
+        entry.count -= taken;
+        if entry.count > 0 {
+            return Some((entry.item.clone(), taken));
         }
-        if let Some(ref mut i) = self.equipped_armor {
-            if *i > removed {
-                *i -= 1;
-            } else if *i == removed {
-                self.equipped_armor = None;
+
+        self.unequip(uid);
+        Some((self.inventory.remove(index).item, taken))
+    }
+
+    pub fn equip(&mut self, uid: u32) -> bool {
+        let Some(item) = self.get_item(uid) else {
+            return false;
+        };
+        let slot = match item.kind {
+            ItemKind::Weapon => &mut self.equipped_weapon,
+            ItemKind::Armor => &mut self.equipped_armor,
+            ItemKind::Accessory => &mut self.equipped_accessory,
+            ItemKind::Consumable | ItemKind::Quest => return false,
+        };
+        *slot = Some(uid);
+        true
+    }
+
+    pub fn unequip(&mut self, uid: u32) {
+        for slot in [
+            &mut self.equipped_weapon,
+            &mut self.equipped_armor,
+            &mut self.equipped_accessory,
+        ] {
+            if *slot == Some(uid) {
+                *slot = None;
             }
         }
-        if let Some(ref mut i) = self.equipped_accessory {
-            if *i > removed {
-                *i -= 1;
-            } else if *i == removed {
-                self.equipped_accessory = None;
+    }
+
+    /// 장비를 바꿨을 때 공격력/방어력 변화. 장착 중인 장비는 벗었을 때의 변화.
+    /// 장비가 아니면 None
+    pub fn equip_delta(&self, uid: u32) -> Option<(i32, i32)> {
+        let item = self.get_item(uid)?;
+        let current = match item.kind {
+            ItemKind::Weapon => self.get_weapon(),
+            ItemKind::Armor => self.get_armor(),
+            ItemKind::Accessory => self.get_accessory(),
+            ItemKind::Consumable | ItemKind::Quest => return None,
+        };
+
+        let mut before = EquipmentStats::default();
+        let mut after = EquipmentStats::default();
+        if let Some(current) = current {
+            before.add_item(current);
+        }
+        if !self.is_equipped(uid) {
+            after.add_item(item);
+        }
+        Some((after.atk() - before.atk(), after.def() - before.def()))
+    }
+
+    /// 소비 아이템은 사용하고, 장비는 장착한다
+    pub fn use_item(&mut self, uid: u32) -> bool {
+        let Some(item) = self.get_item(uid) else {
+            return false;
+        };
+
+        match item.kind {
+            ItemKind::Consumable => {
+                let (heal, status) = (item.param1, item.status);
+                self.stats.heal(heal);
+                if let Some(effect) = status {
+                    self.status.apply(effect);
+                }
+                self.take_item(uid);
+                true
             }
+            ItemKind::Quest => false,
+            _ => self.equip(uid),
         }
     }
 
@@ -134,6 +419,9 @@ impl Player {
         let new_x = (self.x as i32 + dx) as usize;
         let new_y = (self.y as i32 + dy) as usize;
         map.get_tile(new_x, new_y).is_passable()
+            && map
+                .gate_conditions(new_x, new_y)
+                .is_none_or(|conditions| self.check_conditions(conditions))
     }
 
     pub fn move_by(&mut self, dx: i32, dy: i32) {
@@ -171,6 +459,12 @@ impl Player {
             .any(|q| q.quest_id == quest_id && q.completed)
     }
 
+    pub fn is_quest_rewarded(&self, quest_id: &str) -> bool {
+        self.quests
+            .iter()
+            .any(|q| q.quest_id == quest_id && q.rewarded)
+    }
+
     pub fn add_quest(&mut self, quest_id: &str) {
         if !self.has_quest(quest_id) {
             self.quests.push(QuestProgress {
@@ -182,23 +476,226 @@ impl Player {
         }
     }
 
+    /// 완료했지만 아직 보상을 받지 않은 퀘스트
+    pub fn can_turn_in_quest(&self, quest_id: &str) -> bool {
+        self.quests
+            .iter()
+            .any(|q| q.quest_id == quest_id && q.completed && !q.rewarded)
+    }
+
+    pub fn check_condition(&self, condition: &DialogCondition) -> bool {
+        match condition {
+            DialogCondition::HasQuest(id) => self.has_quest(id),
+            DialogCondition::QuestComplete(id) => self.is_quest_complete(id),
+            DialogCondition::QuestRewarded(id) => self.is_quest_rewarded(id),
+            DialogCondition::HasItem(id, count) => self.count_item(id) >= *count,
+            DialogCondition::HasGold(amount) => self.stats.gold >= *amount,
+            DialogCondition::Flag(name) => self.has_flag(name),
+            DialogCondition::VarAtLeast(name, value) => self.var(name) >= *value,
+            DialogCondition::Not(inner) => !self.check_condition(inner),
+        }
+    }
+
+    /// 모든 조건을 만족하는지 (빈 목록은 항상 참)
+    pub fn check_conditions(&self, conditions: &[DialogCondition]) -> bool {
+        conditions.iter().all(|c| self.check_condition(c))
+    }
+
     pub fn complete_quest(&mut self, quest_id: &str) {
         if let Some(q) = self.quests.iter_mut().find(|q| q.quest_id == quest_id) {
             q.rewarded = true;
         }
     }
 
-    pub fn has_item(&self, item_id: &str) -> bool {
-        self.inventory.iter().any(|i| i.id == item_id)
+    pub fn count_item(&self, item_id: &str) -> u32 {
+        self.inventory
+            .iter()
+            .filter(|e| e.item.id == item_id)
+            .map(|e| e.count)
+            .sum()
+    }
+
+    /// id로 아이템 count개를 없앤다. 모자라면 아무것도 없애지 않는다.
+    pub fn remove_items(&mut self, item_id: &str, count: u32) -> bool {
+        if self.count_item(item_id) < count {
+            return false;
+        }
+        for _ in 0..count {
+            self.remove_item(item_id);
+        }
+        true
     }
 
+    /// id로 아이템 하나를 없앤다. 장비하지 않은 것부터 고른다.
     pub fn remove_item(&mut self, item_id: &str) -> bool {
-        if let Some(idx) = self.inventory.iter().position(|i| i.id == item_id) {
-            self.inventory.remove(idx);
-            self.fix_equipped_indices(idx);
-            true
-        } else {
-            false
+        let uid = self
+            .inventory
+            .iter()
+            .filter(|e| e.item.id == item_id)
+            .min_by_key(|e| self.is_equipped(e.uid))
+            .map(|e| e.uid);
+
+        match uid {
+            Some(uid) => self.take_item(uid).is_some(),
+            None => false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, kind: ItemKind) -> Item {
+        Item {
+            id: id.into(),
+            name: id.into(),
+            kind,
+            param1: 5,
+            param2: 0,
+            price: 10,
+            max_stack: if kind == ItemKind::Consumable { 3 } else { 1 },
+            status: None,
+        }
+    }
+
+    #[test]
+    fn equipment_survives_removing_other_items() {
+        let mut player = Player::new("Hero".into(), "village");
+        let potion = player.add_item(item("potion", ItemKind::Consumable));
+        let sword = player.add_item(item("iron_sword", ItemKind::Weapon));
+        let spare = player.add_item(item("iron_sword", ItemKind::Weapon));
+        player.equip(sword);
+
+        assert!(player.take_item(potion).is_some());
+        assert_eq!(
+            player.get_weapon().map(|w| w.id.as_str()),
+            Some("iron_sword")
+        );
+        assert!(player.is_equipped(sword));
+
+        // id로 지울 때는 장비하지 않은 쪽을 먼저 없앤다
+        assert!(player.remove_item("iron_sword"));
+        assert!(player.get_item(spare).is_none());
+        assert!(player.is_equipped(sword));
+
+        player.take_item(sword);
+        assert!(player.get_weapon().is_none());
+    }
+
+    #[test]
+    fn consumables_stack_up_to_max() {
+        let mut player = Player::new("Hero".into(), "village");
+        let first = player.add_items(item("potion", ItemKind::Consumable), 2);
+        player.add_items(item("potion", ItemKind::Consumable), 2);
+
+        let counts: Vec<u32> = player.inventory().iter().map(|e| e.count).collect();
+        assert_eq!(counts, [3, 1]);
+        assert_eq!(player.count_item("potion"), 4);
+
+        assert!(!player.remove_items("potion", 5));
+        assert_eq!(player.count_item("potion"), 4);
+
+        assert!(player.use_item(first));
+        assert_eq!(player.inventory()[0].count, 2);
+        assert!(player.remove_items("potion", 3));
+        assert!(player.inventory().is_empty());
+    }
+
+    #[test]
+    fn take_items_stops_at_stack_size() {
+        let mut player = Player::new("Hero".into(), "village");
+        let stack = player.add_items(item("potion", ItemKind::Consumable), 2);
+
+        let (potion, taken) = player.take_items(stack, 5).unwrap();
+        assert_eq!((potion.id.as_str(), taken), ("potion", 2));
+        assert!(player.inventory().is_empty());
+        assert!(player.take_items(stack, 1).is_none());
+    }
+
+    #[test]
+    fn falling_costs_gold_and_returns_to_respawn() {
+        let maps = crate::data::parse_maps(
+            "@MAP:field:들판\nP..\n@END\n@MAP:town:마을\n.P.\n@TOWN\n@END\n",
+        );
+        let mut player = Player::new("Hero".into(), "field");
+        player.stats.gold = 95;
+        player.stats.current_hp = 0;
+
+        assert_eq!(player.fall(), Some(9));
+        assert_eq!(player.stats.gold, 86);
+        assert_eq!(player.stats.current_hp, player.stats.max_hp);
+        assert_eq!(player.deaths, 1);
+
+        // 되살아날 곳이 없으면 마을의 시작 지점
+        let (map, point) = player.respawn_target(&maps).unwrap();
+        assert_eq!((map.id.as_str(), point), ("town", None));
+
+        player.current_map_id = "field".into();
+        player.x = 2;
+        player.set_respawn_here();
+        let (map, point) = player.respawn_target(&maps).unwrap();
+        assert_eq!((map.id.as_str(), point), ("field", Some((2, 0))));
+
+        player.respawn = Some(("removed".into(), 1, 1));
+        assert_eq!(player.respawn_target(&maps).unwrap().0.id, "town");
+
+        player.hardcore = true;
+        assert_eq!(player.fall(), None);
+        assert_eq!((player.deaths, player.stats.gold), (2, 86));
+    }
+
+    #[test]
+    fn equip_delta_compares_with_current_gear() {
+        let mut player = Player::new("Hero".into(), "village");
+        let ring = player.add_item(item("ring", ItemKind::Accessory));
+        let mut strong = item("power_ring", ItemKind::Accessory);
+        strong.param1 = 8;
+        let strong = player.add_item(strong);
+        let potion = player.add_item(item("potion", ItemKind::Consumable));
+
+        assert_eq!(player.equip_delta(ring), Some((5, 0)));
+        player.equip(ring);
+        assert_eq!(player.equip_delta(strong), Some((3, 0)));
+        assert_eq!(player.equip_delta(ring), Some((-5, 0)));
+        assert_eq!(player.equip_delta(potion), None);
+
+        player.unequip(ring);
+        assert!(player.get_accessory().is_none());
+    }
+
+    #[test]
+    fn gates_open_when_flag_is_set() {
+        let data = "@MAP:m\n#P.#\n@GATE:2:0:FLAG=door&!VAR_GE=alarm*2\n@END\n";
+        let map = crate::data::parse_maps(data).remove(0);
+        let mut player = Player::new("Hero".into(), "m");
+        player.spawn_at_map(&map, None);
+
+        assert!(!player.can_move(&map, 1, 0));
+        player.set_var("door", 1);
+        assert!(player.can_move(&map, 1, 0));
+        player.add_var("alarm", 1);
+        player.add_var("alarm", 1);
+        assert!(!player.can_move(&map, 1, 0));
+    }
+
+    #[test]
+    fn big_exp_grant_levels_up_repeatedly_until_cap() {
+        let levels = crate::data::parse_levels("2:100:10:5:2:1\n3:200:10:5:2:1\n4:300:20:5:3:2\n");
+        let mut stats = PlayerStats {
+            current_hp: 1,
+            ..PlayerStats::default()
+        };
+
+        let up = stats.add_exp(350, &levels).unwrap();
+        assert_eq!((up.from, up.to, up.hp, up.atk), (1, 3, 20, 4));
+        assert_eq!((stats.level, stats.exp, stats.exp_to_next), (3, 50, 300));
+        assert_eq!(stats.current_hp, stats.max_hp);
+        assert!(stats.add_exp(10, &levels).is_none());
+
+        // 최대 레벨에서는 경험치가 쌓이지 않는다
+        let up = stats.add_exp(1000, &levels).unwrap();
+        assert_eq!(up.to, 4);
+        assert_eq!((stats.level, stats.exp, stats.exp_to_next), (4, 0, 0));
+    }
+}
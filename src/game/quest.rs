@@ -1,4 +1,5 @@
 use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
 use wipi::framebuffer::Framebuffer;
 
@@ -7,7 +8,7 @@ use super::renderer::{
     COLOR_BLACK, COLOR_GRAY, COLOR_GREEN, COLOR_WHITE, COLOR_YELLOW, clear_screen, draw_rect,
     draw_text, fill_rect,
 };
-use crate::data::Quest;
+use crate::data::{Quest, QuestProgress, QuestType};
 
 pub fn draw_quest_log(fb: &mut Framebuffer, player: &Player, quests: &[Quest]) {
     clear_screen(fb);
@@ -43,7 +44,7 @@ pub fn draw_quest_log(fb: &mut Framebuffer, player: &Player, quests: &[Quest]) {
             };
             draw_text(fb, 8, y, &quest.name, status_color1);
 
-            let progress_text = format!("{}/{}", progress.current_count, quest.target_count);
+            let progress_text = progress_text(quest, progress);
             draw_text(fb, screen_w - 40, y, &progress_text, status_color2);
 
             let desc_y = y + 10;
@@ -59,3 +60,185 @@ pub fn draw_quest_log(fb: &mut Framebuffer, player: &Player, quests: &[Quest]) {
 
     draw_text(fb, 8, screen_h - 14, "Back:Close", COLOR_GRAY);
 }
+
+fn progress_text(quest: &Quest, progress: &QuestProgress) -> String {
+    match quest.quest_type {
+        QuestType::Kill | QuestType::Collect => {
+            format!("{}/{}", progress.current_count, quest.target_count)
+        }
+        QuestType::Talk | QuestType::Reach if progress.completed => "Done".into(),
+        QuestType::Talk => "Talk".into(),
+        QuestType::Reach => "Go".into(),
+    }
+}
+
+/// 적을 처치할 때마다 KILL 퀘스트 진행도를 올린다
+pub fn record_kill(player: &mut Player, quests: &[Quest], enemy_id: &str) {
+    for progress in &mut player.quests {
+        if progress.completed || progress.rewarded {
+            continue;
+        }
+        if let Some(quest) = quests.iter().find(|q| q.id == progress.quest_id)
+            && quest.quest_type == QuestType::Kill
+            && quest.target_id == enemy_id
+        {
+            progress.current_count += 1;
+            progress.completed = progress.current_count >= quest.target_count;
+        }
+    }
+}
+
+/// TALK 퀘스트는 퀘스트마다 처음 말을 건 한 번만 센다
+pub fn record_talk(player: &mut Player, quests: &[Quest], npc_id: &str) {
+    for progress in &mut player.quests {
+        if progress.completed || progress.rewarded {
+            continue;
+        }
+        if let Some(quest) = quests.iter().find(|q| q.id == progress.quest_id)
+            && quest.quest_type == QuestType::Talk
+            && quest.target_id == npc_id
+        {
+            progress.current_count = quest.target_count;
+            progress.completed = true;
+        }
+    }
+}
+
+/// COLLECT 퀘스트 진행도를 지금 가진 아이템 수에 맞춘다
+pub fn update_collect_quests(player: &mut Player, quests: &[Quest]) {
+    for i in 0..player.quests.len() {
+        let progress = &player.quests[i];
+        if progress.rewarded {
+            continue;
+        }
+        if let Some(quest) = quests.iter().find(|q| q.id == progress.quest_id)
+            && quest.quest_type == QuestType::Collect
+        {
+            let held = player.count_item(&quest.target_id) as i32;
+            let progress = &mut player.quests[i];
+            progress.current_count = held.min(quest.target_count);
+            progress.completed = held >= quest.target_count;
+        }
+    }
+}
+
+/// 목표 맵(또는 칸)에 들어서면 REACH 퀘스트를 끝낸다
+pub fn update_reach_quests(player: &mut Player, quests: &[Quest]) {
+    for i in 0..player.quests.len() {
+        let progress = &player.quests[i];
+        if progress.completed || progress.rewarded {
+            continue;
+        }
+        if let Some(quest) = quests.iter().find(|q| q.id == progress.quest_id)
+            && quest.quest_type == QuestType::Reach
+        {
+            let (map_id, pos) = quest.reach_target();
+            let reached = map_id == player.current_map_id
+                && pos.is_none_or(|(x, y)| x == player.x && y == player.y);
+            if reached {
+                let progress = &mut player.quests[i];
+                progress.current_count = quest.target_count;
+                progress.completed = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Item, ItemKind};
+
+    fn quest(id: &str, quest_type: QuestType, target_id: &str, count: i32) -> Quest {
+        Quest {
+            id: id.into(),
+            name: id.into(),
+            quest_type,
+            target_id: target_id.into(),
+            target_count: count,
+            reward_exp: 0,
+            reward_gold: 0,
+            description: String::new(),
+            reward_item: None,
+        }
+    }
+
+    fn herb() -> Item {
+        Item {
+            id: "herb".into(),
+            name: "herb".into(),
+            kind: ItemKind::Consumable,
+            param1: 0,
+            param2: 0,
+            price: 1,
+            max_stack: 9,
+            status: None,
+        }
+    }
+
+    fn progress<'a>(player: &'a Player, id: &str) -> &'a QuestProgress {
+        player.quests.iter().find(|q| q.quest_id == id).unwrap()
+    }
+
+    #[test]
+    fn collect_follows_items_held() {
+        let quests = [quest("q", QuestType::Collect, "herb", 2)];
+        let mut player = Player::new("Hero".into(), "village");
+        player.add_item(herb());
+        player.add_quest("q");
+        update_collect_quests(&mut player, &quests);
+        assert_eq!(progress(&player, "q").current_count, 1);
+        assert!(!progress(&player, "q").completed);
+
+        player.add_item(herb());
+        update_collect_quests(&mut player, &quests);
+        assert!(progress(&player, "q").completed);
+
+        // 아이템을 잃으면 다시 미완료가 된다
+        player.remove_item("herb");
+        update_collect_quests(&mut player, &quests);
+        assert_eq!(progress(&player, "q").current_count, 1);
+        assert!(!progress(&player, "q").completed);
+    }
+
+    #[test]
+    fn talk_counts_once_per_quest() {
+        let quests = [
+            quest("q", QuestType::Talk, "elder", 1),
+            quest("other", QuestType::Talk, "guard", 1),
+        ];
+        let mut player = Player::new("Hero".into(), "village");
+        player.add_quest("q");
+        player.add_quest("other");
+
+        record_talk(&mut player, &quests, "elder");
+        record_talk(&mut player, &quests, "elder");
+        assert!(progress(&player, "q").completed);
+        assert_eq!(progress(&player, "q").current_count, 1);
+        assert!(!progress(&player, "other").completed);
+    }
+
+    #[test]
+    fn reach_needs_map_and_tile() {
+        let quests = [
+            quest("map", QuestType::Reach, "forest", 1),
+            quest("tile", QuestType::Reach, "forest@3,4", 1),
+        ];
+        let mut player = Player::new("Hero".into(), "village");
+        player.add_quest("map");
+        player.add_quest("tile");
+
+        update_reach_quests(&mut player, &quests);
+        assert!(!progress(&player, "map").completed);
+
+        player.current_map_id = "forest".into();
+        update_reach_quests(&mut player, &quests);
+        assert!(progress(&player, "map").completed);
+        assert!(!progress(&player, "tile").completed);
+
+        player.x = 3;
+        player.y = 4;
+        update_reach_quests(&mut player, &quests);
+        assert!(progress(&player, "tile").completed);
+    }
+}
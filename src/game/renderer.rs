@@ -108,6 +108,25 @@ pub fn draw_hp_bar(fb: &mut Framebuffer, x: i32, y: i32, w: i32, current: i32, m
     fb.draw_rect(x, y, w, 4, COLOR_WHITE);
 }
 
+/// draw_fade의 단계 수. 이 단계에서 화면이 완전히 검어진다
+pub const FADE_STEPS: u32 = 4;
+
+/// 가로줄을 FADE_STEPS줄마다 level줄씩 검게 칠해 화면을 어둡게 한다
+pub fn draw_fade(fb: &mut Framebuffer, level: u32) {
+    if level >= FADE_STEPS {
+        clear_screen(fb);
+        return;
+    }
+
+    let w = fb.width() as i32;
+    let h = fb.height() as i32;
+    for y in 0..h {
+        if (y as u32 % FADE_STEPS) < level {
+            fb.fill_rect(0, y, w, 1, COLOR_BLACK);
+        }
+    }
+}
+
 pub fn draw_selection_cursor(fb: &mut Framebuffer, x: i32, y: i32) {
     fb.fill_rect(x, y + 2, 4, 4, COLOR_WHITE);
 }
@@ -0,0 +1,114 @@
+/// 게임플레이용 의사 난수 생성기 (xorshift64*)
+/// 같은 시드는 항상 같은 결과를 만든다.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64로 시드를 섞어서 작은 시드 값도 고르게 퍼지게 한다
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self::from_state(z)
+    }
+
+    /// 저장된 내부 상태에서 이어서 생성한다
+    pub fn from_state(state: u64) -> Self {
+        Self {
+            state: if state == 0 {
+                0x2545_F491_4F6C_DD1D
+            } else {
+                state
+            },
+        }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+
+    /// 0 이상 bound 미만
+    pub fn below(&mut self, bound: u32) -> u32 {
+        if bound == 0 {
+            return 0;
+        }
+        ((self.next_u32() as u64 * bound as u64) >> 32) as u32
+    }
+
+    /// min 이상 max 이하
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        min + self.below((max - min + 1) as u32) as i32
+    }
+
+    pub fn percent(&mut self, chance: i32) -> bool {
+        (self.below(100) as i32) < chance
+    }
+
+    /// (값, 가중치) 목록에서 가중치에 비례해 하나를 고른다
+    pub fn pick_weighted<'a, T>(&mut self, entries: &'a [(T, i32)]) -> Option<&'a T> {
+        let total: i32 = entries.iter().map(|(_, w)| (*w).max(0)).sum();
+        if total <= 0 {
+            return None;
+        }
+
+        let mut roll = self.below(total as u32) as i32;
+        for (value, weight) in entries {
+            let weight = (*weight).max(0);
+            if roll < weight {
+                return Some(value);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+
+        let mut c = Rng::from_state(a.state());
+        assert_eq!(a.range(0, 1000), c.range(0, 1000));
+    }
+
+    #[test]
+    fn weighted_pick_respects_weights() {
+        let mut rng = Rng::new(7);
+        let entries = [("never", 0), ("slime", 3), ("goblin", 1)];
+        let mut slimes = 0;
+        for _ in 0..1000 {
+            let pick = *rng.pick_weighted(&entries).unwrap();
+            assert_ne!(pick, "never");
+            if pick == "slime" {
+                slimes += 1;
+            }
+        }
+        assert!((650..850).contains(&slimes));
+
+        for _ in 0..100 {
+            assert!((5..=8).contains(&rng.range(5, 8)));
+        }
+    }
+}
@@ -1,41 +1,231 @@
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
 
 use wipi::database::{Database, OpenMode};
 
-use super::Player;
+use super::{Player, Rng};
 use crate::data::{Item, ItemKind, QuestProgress};
 
-const SAVE_DB_NAME: &str = "save";
+pub const SAVE_SLOT_COUNT: usize = 3;
 
-pub fn save_game(player: &Player) -> bool {
-    let data = serialize_save(player);
+/// 슬롯 기능 이전의 단일 저장 파일. 0번 슬롯으로 읽는다.
+const LEGACY_SAVE_DB_NAME: &str = "save";
 
-    if let Ok(mut db) = Database::open(SAVE_DB_NAME, OpenMode::ReadWrite) {
+/// 현재 저장 형식 버전. 0은 버전 헤더와 체크섬이 없던 예전 형식
+pub const SAVE_VERSION: u32 = 1;
+const SAVE_MAGIC: &str = "WIPISAVE";
+
+const INITIAL_READ_SIZE: usize = 1024;
+const MAX_READ_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    Missing,
+    TooLarge,
+    Corrupted,
+    UnsupportedVersion(u32),
+    /// 하드코어 캐릭터가 쓰러져 끝난 판
+    Ended,
+}
+
+/// 플레이 시간 표시용. on_paint 호출 횟수를 초로 환산한다.
+pub const TICKS_PER_SECOND: u32 = 20;
+
+/// 슬롯 선택 화면에 표시할 저장 요약
+#[derive(Debug, Clone)]
+pub struct SaveSummary {
+    pub name: String,
+    pub level: i32,
+    pub map_name: String,
+    pub play_ticks: u32,
+    pub corrupted: bool,
+    pub ended: bool,
+}
+
+impl SaveSummary {
+    fn corrupted() -> Self {
+        Self {
+            name: String::new(),
+            level: 0,
+            map_name: String::new(),
+            play_ticks: 0,
+            corrupted: true,
+            ended: false,
+        }
+    }
+
+    pub fn play_time_text(&self) -> String {
+        let minutes = self.play_ticks / TICKS_PER_SECOND / 60;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+fn slot_db_name(slot: usize) -> String {
+    format!("save{}", slot)
+}
+
+fn open_slot(slot: usize) -> Option<Database> {
+    Database::open(&slot_db_name(slot), OpenMode::ReadOnly)
+        .or_else(|e| {
+            if slot == 0 {
+                Database::open(LEGACY_SAVE_DB_NAME, OpenMode::ReadOnly)
+            } else {
+                Err(e)
+            }
+        })
+        .ok()
+}
+
+/// 저장 파일 전체를 읽는다. 버퍼가 가득 차면 크기를 두 배로 늘려 다시 읽는다.
+fn read_slot(slot: usize) -> Result<String, LoadError> {
+    let mut size = INITIAL_READ_SIZE;
+    loop {
+        let db = open_slot(slot).ok_or(LoadError::Missing)?;
+        let mut buf = vec![0u8; size];
+        let len = db.read(&mut buf).map_err(|_| LoadError::Corrupted)?;
+
+        if len < size {
+            buf.truncate(len);
+            return String::from_utf8(buf).map_err(|_| LoadError::Corrupted);
+        }
+        if size >= MAX_READ_SIZE {
+            return Err(LoadError::TooLarge);
+        }
+        size *= 2;
+    }
+}
+
+pub fn save_game(slot: usize, player: &Player, rng: &Rng, map_name: &str) -> bool {
+    let data = serialize_save(player, rng, map_name);
+
+    if let Ok(mut db) = Database::open(&slot_db_name(slot), OpenMode::ReadWrite) {
         db.write(data.as_bytes()).is_ok()
     } else {
         false
     }
 }
 
-pub fn load_game(player: &mut Player) -> bool {
-    if let Ok(db) = Database::open(SAVE_DB_NAME, OpenMode::ReadOnly) {
-        let mut buf = [0u8; 1024];
-        if let Ok(len) = db.read(&mut buf)
-            && let Ok(data) = core::str::from_utf8(&buf[..len])
-        {
-            return deserialize_save(data, player);
+/// 저장 파일을 읽어 적용한다. 실패하면 player와 rng는 그대로 둔다.
+pub fn load_game(
+    slot: usize,
+    player: &mut Player,
+    rng: &mut Rng,
+    items: &[Item],
+) -> Result<(), LoadError> {
+    let data = read_slot(slot)?;
+    let (loaded_player, loaded_rng) = deserialize_save(&data, rng, items)?;
+    *player = loaded_player;
+    *rng = loaded_rng;
+    Ok(())
+}
+
+pub fn has_save_data() -> bool {
+    (0..SAVE_SLOT_COUNT).any(|slot| open_slot(slot).is_some())
+}
+
+/// 각 슬롯의 요약. 비어 있는 슬롯은 None
+pub fn read_save_summaries() -> Vec<Option<SaveSummary>> {
+    (0..SAVE_SLOT_COUNT)
+        .map(|slot| match read_slot(slot) {
+            Ok(data) => Some(parse_summary(&data)),
+            Err(LoadError::Missing) => None,
+            Err(_) => Some(SaveSummary::corrupted()),
+        })
+        .collect()
+}
+
+fn parse_summary(data: &str) -> SaveSummary {
+    let Ok((_, body)) = verify_save(data) else {
+        return SaveSummary::corrupted();
+    };
+
+    let mut summary = SaveSummary {
+        name: String::new(),
+        level: 1,
+        map_name: String::new(),
+        play_ticks: 0,
+        corrupted: false,
+        ended: false,
+    };
+
+    for line in body.lines() {
+        let parts: Vec<&str> = line.trim().split(':').collect();
+        match parts[0] {
+            "HEADER" if parts.len() >= 5 => {
+                summary.name = parts[1].into();
+                summary.level = parts[2].parse().unwrap_or(1);
+                summary.map_name = parts[3].into();
+                summary.play_ticks = parts[4].parse().unwrap_or(0);
+            }
+            // 헤더가 없는 예전 저장 파일
+            "PLAYER" if parts.len() >= 3 => {
+                summary.name = parts[1].into();
+                summary.map_name = parts[2].into();
+            }
+            "STATS" if parts.len() >= 2 => {
+                summary.level = parts[1].parse().unwrap_or(1);
+            }
+            "DEATHS" if parts.len() >= 3 => {
+                summary.ended = is_ended(parts[1], parts[2]);
+            }
+            _ => {}
         }
     }
-    false
+
+    summary
 }
 
-pub fn has_save_data() -> bool {
-    Database::open(SAVE_DB_NAME, OpenMode::ReadOnly).is_ok()
+/// FNV-1a 32비트 해시
+fn checksum(data: &str) -> u32 {
+    data.bytes().fold(0x811C_9DC5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
 }
 
-fn serialize_save(player: &Player) -> String {
-    let mut lines = Vec::new();
+/// 버전 헤더와 체크섬을 확인하고 (버전, 체크섬을 제외한 본문)을 돌려준다.
+/// 버전 헤더가 없으면 체크섬도 없던 v0 형식으로 본다.
+fn verify_save(data: &str) -> Result<(u32, &str), LoadError> {
+    let first = data.lines().next().unwrap_or("").trim();
+    let version = match first.strip_prefix(SAVE_MAGIC) {
+        Some(rest) => rest
+            .strip_prefix(':')
+            .and_then(|v| v.parse().ok())
+            .ok_or(LoadError::Corrupted)?,
+        None => return Ok((0, data)),
+    };
+
+    if version > SAVE_VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let split = data.rfind("\nCHECKSUM:").ok_or(LoadError::Corrupted)?;
+    let body = &data[..split + 1];
+    let stored = data[split + 1..]
+        .trim()
+        .strip_prefix("CHECKSUM:")
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or(LoadError::Corrupted)?;
+
+    if stored != checksum(body) {
+        return Err(LoadError::Corrupted);
+    }
+    Ok((version, body))
+}
+
+fn serialize_save(player: &Player, rng: &Rng, map_name: &str) -> String {
+    let version = format!("{}:{}", SAVE_MAGIC, SAVE_VERSION);
+    let mut lines = vec![
+        version,
+        format_args_to_string(&[
+            "HEADER",
+            &player.name,
+            &player.stats.level.to_string(),
+            map_name,
+            &player.play_ticks.to_string(),
+        ]),
+    ];
 
     lines.push(format_args_to_string(&[
         "PLAYER",
@@ -58,38 +248,27 @@ fn serialize_save(player: &Player) -> String {
         &player.stats.gold.to_string(),
     ]));
 
+    // 장비는 ITEM 줄의 순번으로 저장한다
+    let equipped_index = |kind: ItemKind| {
+        player
+            .inventory()
+            .iter()
+            .position(|e| e.item.kind == kind && player.is_equipped(e.uid))
+            .map(|i| i.to_string())
+            .unwrap_or_else(|| "-1".into())
+    };
     lines.push(format_args_to_string(&[
         "EQUIP",
-        &player
-            .equipped_weapon
-            .map(|i| i.to_string())
-            .unwrap_or_else(|| "-1".into()),
-        &player
-            .equipped_armor
-            .map(|i| i.to_string())
-            .unwrap_or_else(|| "-1".into()),
-        &player
-            .equipped_accessory
-            .map(|i| i.to_string())
-            .unwrap_or_else(|| "-1".into()),
+        &equipped_index(ItemKind::Weapon),
+        &equipped_index(ItemKind::Armor),
+        &equipped_index(ItemKind::Accessory),
     ]));
 
-    for item in &player.inventory {
-        let kind_char = match item.kind {
-            ItemKind::Weapon => "W",
-            ItemKind::Armor => "A",
-            ItemKind::Accessory => "C",
-            ItemKind::Consumable => "I",
-        };
+    for entry in player.inventory() {
         lines.push(format_args_to_string(&[
             "ITEM",
-            kind_char,
-            &item.id,
-            &item.name,
-            &item.param1.to_string(),
-            &item.param2.to_string(),
-            &item.param3.to_string(),
-            &item.price.to_string(),
+            &entry.item.id,
+            &entry.count.to_string(),
         ]));
     }
 
@@ -112,14 +291,62 @@ fn serialize_save(player: &Player) -> String {
         ]));
     }
 
+    for enemy_id in &player.defeated_bosses {
+        lines.push(format_args_to_string(&["BOSS", enemy_id]));
+    }
+
+    for (map_id, index) in &player.defeated_patrols {
+        lines.push(format_args_to_string(&[
+            "PATROL",
+            map_id,
+            &index.to_string(),
+        ]));
+    }
+
+    for (map_id, index) in &player.fired_triggers {
+        lines.push(format_args_to_string(&[
+            "TRIGGER",
+            map_id,
+            &index.to_string(),
+        ]));
+    }
+
+    for (name, value) in &player.vars {
+        lines.push(format_args_to_string(&["VAR", name, &value.to_string()]));
+    }
+
+    lines.push(format_args_to_string(&[
+        "DEATHS",
+        &player.deaths.to_string(),
+        if player.hardcore { "1" } else { "0" },
+    ]));
+
+    if let Some((map_id, x, y)) = &player.respawn {
+        lines.push(format_args_to_string(&[
+            "RESPAWN",
+            map_id,
+            &x.to_string(),
+            &y.to_string(),
+        ]));
+    }
+
+    lines.push(format_args_to_string(&["SEED", &rng.state().to_string()]));
+
     let mut result = String::new();
     for line in lines {
         result.push_str(&line);
         result.push('\n');
     }
+    let sum = checksum(&result);
+    result.push_str(&format!("CHECKSUM:{:08x}\n", sum));
     result
 }
 
+/// DEATHS 줄의 (쓰러진 횟수, 하드코어). 하드코어는 한 번 쓰러지면 판이 끝난다
+fn is_ended(deaths: &str, hardcore: &str) -> bool {
+    hardcore == "1" && deaths.parse::<u32>().is_ok_and(|d| d > 0)
+}
+
 fn format_args_to_string(parts: &[&str]) -> String {
     let mut s = String::new();
     for (i, part) in parts.iter().enumerate() {
@@ -131,28 +358,69 @@ fn format_args_to_string(parts: &[&str]) -> String {
     s
 }
 
-fn deserialize_save(data: &str, player: &mut Player) -> bool {
-    player.inventory.clear();
-    player.quests.clear();
-    player.opened_treasures.clear();
+fn parse_slot_index(value: &str) -> Option<usize> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|&i| i >= 0)
+        .map(|i| i as usize)
+}
+
+/// v0의 `ITEM:kind:id:name:p1:p2:p3:price` 줄. 아이템 테이블에 없는 id면 저장된 값을 그대로 쓴다.
+fn migrate_v0_item(parts: &[&str], items: &[Item]) -> Option<Item> {
+    if let Some(item) = items.iter().find(|i| i.id == parts[2]) {
+        return Some(item.clone());
+    }
 
-    for line in data.lines() {
+    let kind = match parts[1] {
+        "W" => ItemKind::Weapon,
+        "A" => ItemKind::Armor,
+        "C" => ItemKind::Accessory,
+        "I" => ItemKind::Consumable,
+        _ => return None,
+    };
+    Some(Item {
+        id: parts[2].into(),
+        name: parts[3].into(),
+        kind,
+        param1: parts[4].parse().unwrap_or(0),
+        param2: parts[5].parse().unwrap_or(0),
+        price: parts[7].parse().unwrap_or(0),
+        max_stack: 1,
+        status: None,
+    })
+}
+
+/// 새 Player에 읽어 들인다. 아이템은 id로 items에서 찾고, 없어진 아이템은 버린다.
+fn deserialize_save(data: &str, rng: &Rng, items: &[Item]) -> Result<(Player, Rng), LoadError> {
+    let (version, body) = verify_save(data)?;
+
+    let mut player = Player::new(String::new(), "");
+    let mut rng = rng.clone();
+    let mut has_player = false;
+
+    // 저장된 인벤토리 순번 -> 불러온 아이템의 uid
+    let mut item_slots: Vec<Option<u32>> = Vec::new();
+    let mut equipped: [Option<usize>; 3] = [None; 3];
+
+    for line in body.lines() {
         let line = line.trim();
         if line.is_empty() {
             continue;
         }
 
         let parts: Vec<&str> = line.split(':').collect();
-        if parts.is_empty() {
-            continue;
-        }
 
         match parts[0] {
+            "HEADER" if parts.len() >= 5 => {
+                player.play_ticks = parts[4].parse().unwrap_or(0);
+            }
             "PLAYER" if parts.len() >= 5 => {
                 player.name = parts[1].into();
                 player.current_map_id = parts[2].into();
                 player.x = parts[3].parse().unwrap_or(0);
                 player.y = parts[4].parse().unwrap_or(0);
+                has_player = true;
             }
             "STATS" if parts.len() >= 10 => {
                 player.stats.level = parts[1].parse().unwrap_or(1);
@@ -164,42 +432,27 @@ fn deserialize_save(data: &str, player: &mut Player) -> bool {
                 player.stats.base_atk = parts[7].parse().unwrap_or(10);
                 player.stats.base_def = parts[8].parse().unwrap_or(5);
                 player.stats.gold = parts[9].parse().unwrap_or(0);
-                player.stats.exp_to_next = player.stats.level * 100;
             }
             "EQUIP" if parts.len() >= 4 => {
-                player.equipped_weapon = parts[1]
-                    .parse::<i32>()
-                    .ok()
-                    .filter(|&i| i >= 0)
-                    .map(|i| i as usize);
-                player.equipped_armor = parts[2]
-                    .parse::<i32>()
-                    .ok()
-                    .filter(|&i| i >= 0)
-                    .map(|i| i as usize);
-                player.equipped_accessory = parts[3]
-                    .parse::<i32>()
-                    .ok()
-                    .filter(|&i| i >= 0)
-                    .map(|i| i as usize);
-            }
-            "ITEM" if parts.len() >= 8 => {
-                let kind = match parts[1] {
-                    "W" => ItemKind::Weapon,
-                    "A" => ItemKind::Armor,
-                    "C" => ItemKind::Accessory,
-                    "I" => ItemKind::Consumable,
-                    _ => continue,
+                for (slot, value) in equipped.iter_mut().zip(&parts[1..4]) {
+                    *slot = parse_slot_index(value);
+                }
+            }
+            "ITEM" => {
+                let item = if version == 0 && parts.len() >= 8 {
+                    migrate_v0_item(&parts, items)
+                } else if version >= 1 && parts.len() >= 2 {
+                    items.iter().find(|i| i.id == parts[1]).cloned()
+                } else {
+                    None
                 };
-                player.inventory.push(Item {
-                    id: parts[2].into(),
-                    name: parts[3].into(),
-                    kind,
-                    param1: parts[4].parse().unwrap_or(0),
-                    param2: parts[5].parse().unwrap_or(0),
-                    param3: parts[6].parse().unwrap_or(0),
-                    price: parts[7].parse().unwrap_or(0),
-                });
+                // 수량이 없는 줄은 1개 (v0, 겹치기 이전의 v1)
+                let count = match version {
+                    0 => 1,
+                    _ => parts.get(2).and_then(|c| c.parse().ok()).unwrap_or(1),
+                };
+
+                item_slots.push(item.map(|item| player.add_items(item, count)));
             }
             "QUEST" if parts.len() >= 5 => {
                 player.quests.push(QuestProgress {
@@ -215,9 +468,169 @@ fn deserialize_save(data: &str, player: &mut Player) -> bool {
                 let y = parts[3].parse().unwrap_or(0);
                 player.opened_treasures.push((map_id, x, y));
             }
+            "BOSS" if parts.len() >= 2 => {
+                player.defeat_boss(parts[1]);
+            }
+            "PATROL" if parts.len() >= 3 => {
+                if let Ok(index) = parts[2].parse() {
+                    player.defeat_patrol(parts[1], index);
+                }
+            }
+            "TRIGGER" if parts.len() >= 3 => {
+                if let Ok(index) = parts[2].parse() {
+                    player.mark_trigger_fired(parts[1], index);
+                }
+            }
+            "VAR" if parts.len() >= 3 => {
+                player.set_var(parts[1], parts[2].parse().unwrap_or(0));
+            }
+            "DEATHS" if parts.len() >= 3 => {
+                if is_ended(parts[1], parts[2]) {
+                    return Err(LoadError::Ended);
+                }
+                player.deaths = parts[1].parse().unwrap_or(0);
+                player.hardcore = parts[2] == "1";
+            }
+            "RESPAWN" if parts.len() >= 4 => {
+                let x = parts[2].parse().unwrap_or(0);
+                let y = parts[3].parse().unwrap_or(0);
+                player.respawn = Some((parts[1].into(), x, y));
+            }
+            "SEED" if parts.len() >= 2 => {
+                if let Ok(state) = parts[1].parse() {
+                    rng = Rng::from_state(state);
+                }
+            }
             _ => {}
         }
     }
 
-    true
+    if !has_player {
+        return Err(LoadError::Corrupted);
+    }
+
+    for index in equipped.into_iter().flatten() {
+        if let Some(uid) = item_slots.get(index).copied().flatten() {
+            player.equip(uid);
+        }
+    }
+
+    Ok((player, rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, kind: ItemKind, param1: i32) -> Item {
+        Item {
+            id: id.into(),
+            name: id.into(),
+            kind,
+            param1,
+            param2: 0,
+            price: 10,
+            max_stack: if kind == ItemKind::Consumable { 99 } else { 1 },
+            status: None,
+        }
+    }
+
+    fn sample_player() -> Player {
+        let mut player = Player::new("Hero".into(), "village");
+        player.add_items(item("potion", ItemKind::Consumable, 30), 3);
+        let sword = player.add_item(item("iron_sword", ItemKind::Weapon, 5));
+        player.equip(sword);
+        player.stats.gold = 123;
+        player.set_var("met_farmer", 1);
+        player.add_var("wolves", -2);
+        player.deaths = 2;
+        player.respawn = Some(("village".into(), 4, 15));
+        player.mark_trigger_fired("forest", 1);
+        player.defeat_patrol("forest", 0);
+        player
+    }
+
+    #[test]
+    fn round_trip_resolves_items_by_id() {
+        let data = serialize_save(&sample_player(), &Rng::new(1), "Village");
+        assert!(data.starts_with("WIPISAVE:1\n"));
+
+        // 저장 후 밸런스가 바뀐 아이템과 삭제된 아이템
+        let items = [item("iron_sword", ItemKind::Weapon, 9)];
+        let (player, rng) = deserialize_save(&data, &Rng::new(2), &items).unwrap();
+
+        assert_eq!(rng.state(), Rng::new(1).state());
+        assert_eq!(player.stats.gold, 123);
+        assert!(player.has_flag("met_farmer"));
+        assert_eq!(player.var("wolves"), -2);
+        assert_eq!(player.deaths, 2);
+        assert!(!player.hardcore);
+        assert_eq!(player.respawn, Some(("village".into(), 4, 15)));
+        assert!(player.is_trigger_fired("forest", 1));
+        assert!(!player.is_trigger_fired("forest", 0));
+        assert!(player.is_patrol_defeated("forest", 0));
+        assert_eq!(player.inventory().len(), 1);
+        assert!(player.is_equipped(player.inventory()[0].uid));
+        assert_eq!(player.get_weapon().unwrap().param1, 9);
+
+        let items = [
+            item("potion", ItemKind::Consumable, 30),
+            item("iron_sword", ItemKind::Weapon, 5),
+        ];
+        let (player, _) = deserialize_save(&data, &Rng::new(2), &items).unwrap();
+        assert_eq!(player.count_item("potion"), 3);
+        assert_eq!(player.inventory().len(), 2);
+    }
+
+    #[test]
+    fn migrates_v0_layout() {
+        let data = "PLAYER:Hero:cave:3:4\n\
+                    STATS:2:10:60:60:20:20:10:5:50\n\
+                    EQUIP:1:-1:-1\n\
+                    ITEM:I:potion:회복약:30:0:0:50\n\
+                    ITEM:W:old_blade:낡은 칼:4:0:0:80\n";
+        let items = [item("potion", ItemKind::Consumable, 40)];
+        let (player, _) = deserialize_save(data, &Rng::new(0), &items).unwrap();
+
+        assert_eq!(player.current_map_id, "cave");
+        assert_eq!(player.inventory()[0].item.param1, 40);
+        assert_eq!(player.get_weapon().unwrap().id, "old_blade");
+    }
+
+    #[test]
+    fn rejects_corrupted_save() {
+        let data = serialize_save(&sample_player(), &Rng::new(1), "Village");
+        let tampered = data.replace("STATS:1", "STATS:9");
+        assert_eq!(
+            deserialize_save(&tampered, &Rng::new(0), &[]).err(),
+            Some(LoadError::Corrupted)
+        );
+
+        let truncated = &data[..data.len() / 2];
+        assert!(deserialize_save(truncated, &Rng::new(0), &[]).is_err());
+
+        let future = data.replace("WIPISAVE:1", "WIPISAVE:99");
+        assert_eq!(
+            deserialize_save(&future, &Rng::new(0), &[]).err(),
+            Some(LoadError::UnsupportedVersion(99))
+        );
+    }
+
+    #[test]
+    fn fallen_hardcore_save_cannot_be_loaded() {
+        let mut player = sample_player();
+        player.hardcore = true;
+        player.deaths = 0;
+        let data = serialize_save(&player, &Rng::new(1), "Village");
+        assert!(deserialize_save(&data, &Rng::new(0), &[]).is_ok());
+        assert!(!parse_summary(&data).ended);
+
+        assert_eq!(player.fall(), None);
+        let data = serialize_save(&player, &Rng::new(1), "Village");
+        assert_eq!(
+            deserialize_save(&data, &Rng::new(0), &[]).err(),
+            Some(LoadError::Ended)
+        );
+        assert!(parse_summary(&data).ended);
+    }
 }
@@ -1,14 +1,66 @@
 use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use wipi::framebuffer::Framebuffer;
 
 use super::Player;
 use super::renderer::{
-    COLOR_BLACK, COLOR_BLUE, COLOR_DARK_GRAY, COLOR_GRAY, COLOR_GREEN, COLOR_WHITE, COLOR_YELLOW,
-    clear_screen, draw_rect, draw_text, fill_rect,
+    COLOR_BLACK, COLOR_BLUE, COLOR_DARK_GRAY, COLOR_GRAY, COLOR_GREEN, COLOR_RED, COLOR_WHITE,
+    COLOR_YELLOW, clear_screen, draw_rect, draw_text, fill_rect,
 };
-use super::state::{ShopMode, ShopState};
+use super::state::{ShopMode, ShopState, ShopStep};
+use crate::data::Shop;
 
-pub fn draw_shop(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
+/// 상점마다 팔려 나간 수. 재고가 가득 찬 아이템은 기록하지 않는다.
+/// 저장하지 않으므로 게임을 다시 켜면 재고가 모두 채워진다.
+#[derive(Debug, Clone, Default)]
+pub struct ShopStock {
+    sold: Vec<(String, String, u32)>,
+}
+
+impl ShopStock {
+    /// 남은 재고. 재고 제한이 없는 아이템이면 None
+    pub fn remaining(&self, shop: &Shop, item_id: &str) -> Option<u32> {
+        let max = shop.max_stock(item_id)?;
+        let sold = self
+            .sold
+            .iter()
+            .find(|(s, i, _)| *s == shop.id && i == item_id)
+            .map_or(0, |(_, _, sold)| *sold);
+        Some(max.saturating_sub(sold))
+    }
+
+    pub fn take(&mut self, shop: &Shop, item_id: &str, count: u32) {
+        if shop.max_stock(item_id).is_none() || count == 0 {
+            return;
+        }
+        match self
+            .sold
+            .iter_mut()
+            .find(|(s, i, _)| *s == shop.id && i == item_id)
+        {
+            Some((_, _, sold)) => *sold += count,
+            None => self
+                .sold
+                .push((shop.id.clone(), String::from(item_id), count)),
+        }
+    }
+
+    /// 매 틱 부른다. 상점의 restock_ticks마다 팔린 아이템을 하나씩 다시 채운다
+    pub fn restock(&mut self, shops: &[Shop], ticks: u32) {
+        for (shop_id, _, sold) in &mut self.sold {
+            let due = shops.iter().any(|s| {
+                s.id == *shop_id && s.restock_ticks > 0 && ticks.is_multiple_of(s.restock_ticks)
+            });
+            if due {
+                *sold -= 1;
+            }
+        }
+        self.sold.retain(|(_, _, sold)| *sold > 0);
+    }
+}
+
+pub fn draw_shop(fb: &mut Framebuffer, state: &ShopState, player: &Player, stock: &ShopStock) {
     clear_screen(fb);
 
     let screen_w = fb.width() as i32;
@@ -24,10 +76,20 @@ pub fn draw_shop(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
 
     match state.mode {
         ShopMode::Select => draw_mode_select(fb, state),
-        ShopMode::Buy => draw_buy_list(fb, state, player),
+        ShopMode::Buy => draw_buy_list(fb, state, player, stock),
         ShopMode::Sell => draw_sell_list(fb, state, player),
     }
 
+    match state.step {
+        ShopStep::List => {}
+        ShopStep::Quantity | ShopStep::Confirm => draw_quantity_panel(fb, state, player, stock),
+    }
+
+    if let Some((message, ok)) = &state.message {
+        let color = if *ok { COLOR_GREEN } else { COLOR_RED };
+        draw_text(fb, 8, screen_h - 26, message, color);
+    }
+
     draw_text(fb, 8, screen_h - 14, "Back:Exit", COLOR_GRAY);
 }
 
@@ -57,7 +119,7 @@ fn draw_mode_select(fb: &mut Framebuffer, state: &ShopState) {
     draw_text(fb, center_x, 42, "Sell", sell_color);
 }
 
-fn draw_buy_list(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
+fn draw_buy_list(fb: &mut Framebuffer, state: &ShopState, player: &Player, stock: &ShopStock) {
     let screen_w = fb.width() as i32;
 
     draw_text(fb, 8, 18, "== BUY ==", COLOR_GREEN);
@@ -69,7 +131,9 @@ fn draw_buy_list(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
         }
 
         let is_selected = i == state.selected;
-        let can_afford = player.stats.gold >= item.price;
+        let price = state.shop.buy_price(item);
+        let remaining = stock.remaining(&state.shop, &item.id);
+        let can_afford = player.stats.gold >= price && remaining != Some(0);
 
         let (text_color1, text_color2) = if is_selected {
             (COLOR_WHITE, COLOR_WHITE)
@@ -85,7 +149,13 @@ fn draw_buy_list(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
 
         draw_text(fb, 16, y, &item.name, text_color1);
 
-        let price_text = format!("{}G", item.price);
+        match remaining {
+            Some(0) => draw_text(fb, screen_w - 90, y, "Sold out", COLOR_RED),
+            Some(count) => draw_text(fb, screen_w - 70, y, &format!("x{}", count), text_color2),
+            None => {}
+        }
+
+        let price_text = format!("{}G", price);
         draw_text(fb, screen_w - 40, y, &price_text, text_color2);
     }
 }
@@ -95,12 +165,13 @@ fn draw_sell_list(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
 
     draw_text(fb, 8, 18, "== SELL ==", COLOR_BLUE);
 
-    if player.inventory.is_empty() {
+    if player.inventory().is_empty() {
         draw_text(fb, 8, 30, "No items", COLOR_GRAY);
         return;
     }
 
-    for (i, item) in player.inventory.iter().enumerate() {
+    for (i, entry) in player.inventory().iter().enumerate() {
+        let item = &entry.item;
         let y = 30 + (i as i32 * 12);
         if y > 100 {
             break;
@@ -116,11 +187,95 @@ fn draw_sell_list(fb: &mut Framebuffer, state: &ShopState, player: &Player) {
         if is_selected {
             draw_text(fb, 8, y, ">", COLOR_YELLOW);
         }
+        if player.is_equipped(entry.uid) {
+            draw_text(fb, screen_w - 70, y, "E", COLOR_YELLOW);
+        }
 
-        draw_text(fb, 16, y, &item.name, text_color1);
+        let name = if entry.count > 1 {
+            format!("{} x{}", item.name, entry.count)
+        } else {
+            item.name.clone()
+        };
+        draw_text(fb, 16, y, &name, text_color1);
 
-        let sell_price = item.price / 2;
+        let sell_price = state.shop.sell_price(item);
         let price_text = format!("{}G", sell_price);
         draw_text(fb, screen_w - 40, y, &price_text, text_color2);
     }
 }
+
+fn draw_quantity_panel(
+    fb: &mut Framebuffer,
+    state: &ShopState,
+    player: &Player,
+    stock: &ShopStock,
+) {
+    let screen_w = fb.width() as i32;
+    let (x, y, w, h) = (16, 60, screen_w - 32, 40);
+
+    fill_rect(fb, x, y, w, h, COLOR_BLACK);
+    draw_rect(fb, x, y, w, h, COLOR_WHITE);
+
+    let verb = if state.mode == ShopMode::Buy {
+        "Buy"
+    } else {
+        "Sell"
+    };
+    let total = state.total_price(player);
+
+    match state.step {
+        ShopStep::Quantity => {
+            let max = state.max_quantity(player, stock);
+            draw_text(
+                fb,
+                x + 4,
+                y + 4,
+                &format!("{} x{} (max {})", verb, state.quantity, max),
+                COLOR_WHITE,
+            );
+            draw_text(
+                fb,
+                x + 4,
+                y + 16,
+                &format!("Total: {}G", total),
+                COLOR_YELLOW,
+            );
+            draw_text(fb, x + 4, y + 28, "</>:Qty OK:Next", COLOR_GRAY);
+        }
+        ShopStep::Confirm => {
+            draw_text(
+                fb,
+                x + 4,
+                y + 4,
+                &format!("{} x{} for {}G?", verb, state.quantity, total),
+                COLOR_WHITE,
+            );
+            draw_text(fb, x + 4, y + 28, "OK:Yes Back:No", COLOR_GRAY);
+        }
+        ShopStep::List => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stock_sells_out_and_restocks() {
+        let shop = crate::data::parse_shops("shop:상점:100:50:10:potion:sword*2\n").remove(0);
+        let mut stock = ShopStock::default();
+
+        assert_eq!(stock.remaining(&shop, "potion"), None);
+        stock.take(&shop, "sword", 2);
+        assert_eq!(stock.remaining(&shop, "sword"), Some(0));
+
+        // restock_ticks마다 하나씩만 채운다
+        stock.restock(core::slice::from_ref(&shop), 9);
+        assert_eq!(stock.remaining(&shop, "sword"), Some(0));
+        stock.restock(core::slice::from_ref(&shop), 10);
+        assert_eq!(stock.remaining(&shop, "sword"), Some(1));
+        stock.restock(core::slice::from_ref(&shop), 20);
+        stock.restock(core::slice::from_ref(&shop), 30);
+        assert_eq!(stock.remaining(&shop, "sword"), Some(2));
+    }
+}
@@ -0,0 +1,78 @@
+use alloc::format;
+use wipi::framebuffer::Framebuffer;
+
+use super::renderer::{
+    COLOR_BLACK, COLOR_DARK_GRAY, COLOR_GRAY, COLOR_RED, COLOR_WHITE, COLOR_YELLOW, clear_screen,
+    draw_rect, draw_selection_cursor, draw_text, fill_rect,
+};
+use super::state::{SlotMode, SlotSelectState};
+
+pub fn draw_slot_select(fb: &mut Framebuffer, state: &SlotSelectState) {
+    clear_screen(fb);
+
+    let screen_w = fb.width() as i32;
+    let screen_h = fb.height() as i32;
+
+    let title = match state.mode {
+        SlotMode::Load => "LOAD GAME",
+        SlotMode::Save if state.quit_after_save => "SAVE AND QUIT",
+        SlotMode::Save => "SAVE GAME",
+    };
+    draw_text(fb, 10, 4, title, COLOR_YELLOW);
+
+    let slot_h: i32 = 30;
+    let mut y: i32 = 18;
+
+    for (i, summary) in state.summaries.iter().enumerate() {
+        let selected = i == state.selected;
+
+        if selected {
+            draw_selection_cursor(fb, 2, y + 10);
+            fill_rect(fb, 10, y, screen_w - 20, slot_h - 4, COLOR_DARK_GRAY);
+        }
+        draw_rect(
+            fb,
+            10,
+            y,
+            screen_w - 20,
+            slot_h - 4,
+            if selected { COLOR_WHITE } else { COLOR_GRAY },
+        );
+
+        let header = format!("SLOT {}", i + 1);
+        draw_text(fb, 14, y + 2, &header, COLOR_YELLOW);
+
+        match summary {
+            Some(summary) if summary.corrupted => {
+                draw_text(fb, 60, y + 2, "- CORRUPTED -", COLOR_RED);
+            }
+            Some(summary) if summary.ended => {
+                let line1 = format!("{} LV{}", summary.name, summary.level);
+                draw_text(fb, 60, y + 2, &line1, COLOR_GRAY);
+                draw_text(fb, 14, y + 13, "- FALLEN -", COLOR_RED);
+            }
+            Some(summary) => {
+                let line1 = format!("{} LV{}", summary.name, summary.level);
+                draw_text(fb, 60, y + 2, &line1, COLOR_WHITE);
+                let line2 = format!("{} {}", summary.map_name, summary.play_time_text());
+                draw_text(fb, 14, y + 13, &line2, COLOR_GRAY);
+            }
+            None => {
+                draw_text(fb, 60, y + 2, "- EMPTY -", COLOR_GRAY);
+            }
+        }
+
+        y += slot_h;
+    }
+
+    if state.confirm {
+        let box_w = screen_w - 30;
+        let box_y = screen_h / 2 - 14;
+        fill_rect(fb, 15, box_y, box_w, 28, COLOR_BLACK);
+        draw_rect(fb, 15, box_y, box_w, 28, COLOR_RED);
+        draw_text(fb, 20, box_y + 3, "Overwrite this slot?", COLOR_WHITE);
+        draw_text(fb, 20, box_y + 15, "OK:Yes Back:No", COLOR_GRAY);
+    } else {
+        draw_text(fb, 10, screen_h - 12, "OK:Select Back:Return", COLOR_GRAY);
+    }
+}
@@ -1,3 +1,5 @@
+use super::shop::ShopStock;
+use super::{Player, SaveSummary};
 use crate::data::{Dialog, DialogLine, Item, Shop};
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -12,50 +14,131 @@ pub enum GameState {
     Shop(ShopState),
     QuestLog,
     GameOver,
+    ContentError(ContentErrorState),
+    SlotSelect(SlotSelectState),
 }
 
 #[derive(Debug, Clone)]
 pub struct DialogState {
     pub npc_name: String,
-    pub lines: Vec<DialogLine>,
+    pub dialog: Dialog,
     pub current_line: usize,
+    pub selected_choice: usize,
 }
 
 impl DialogState {
-    pub fn new(npc_name: String, dialog: &Dialog) -> Self {
-        Self {
+    /// 조건을 만족하는 첫 줄에서 시작한다. 보여줄 줄이 없으면 None
+    pub fn new(npc_name: String, dialog: &Dialog, player: &Player) -> Option<Self> {
+        let mut state = Self {
             npc_name,
-            lines: dialog.lines.clone(),
+            dialog: dialog.clone(),
             current_line: 0,
-        }
+            selected_choice: 0,
+        };
+        state.skip_hidden(player).then_some(state)
+    }
+
+    fn current(&self) -> Option<&DialogLine> {
+        self.dialog.lines.get(self.current_line)
     }
 
     pub fn current_text(&self) -> Option<&str> {
-        self.lines.get(self.current_line).map(|l| l.text.as_str())
+        self.current().map(|l| l.text.as_str())
     }
 
-    pub fn advance(&mut self) -> bool {
-        if self.current_line + 1 < self.lines.len() {
-            self.current_line += 1;
-            true
-        } else {
-            false
+    pub fn current_actions(&self) -> &[crate::data::DialogAction] {
+        self.current().map(|l| l.actions.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn choices(&self) -> &[(String, String)] {
+        self.current().map(|l| l.choices.as_slice()).unwrap_or(&[])
+    }
+
+    /// 현재 줄 다음에 대화가 끝나는지 (마지막 줄이거나 END로 이동)
+    pub fn is_last_line(&self) -> bool {
+        self.current().is_none_or(|l| {
+            l.choices.is_empty()
+                && match &l.jump {
+                    Some(label) => self.dialog.label_line(label).is_none(),
+                    None => self.current_line + 1 >= self.dialog.lines.len(),
+                }
+        })
+    }
+
+    pub fn move_choice_up(&mut self) {
+        if self.selected_choice > 0 {
+            self.selected_choice -= 1;
+        }
+    }
+
+    pub fn move_choice_down(&mut self) {
+        if self.selected_choice + 1 < self.choices().len() {
+            self.selected_choice += 1;
+        }
+    }
+
+    /// 고른 선택지나 @JUMP를 따라가고, 없으면 다음 줄로 간다.
+    /// 조건은 이동할 때마다 현재 플레이어 상태로 다시 확인한다. 대화가 끝나면 false
+    pub fn advance(&mut self, player: &Player) -> bool {
+        let Some(line) = self.current() else {
+            return false;
+        };
+
+        let target = match line.choices.get(self.selected_choice) {
+            Some((label, _)) => Some(label),
+            None => line.jump.as_ref(),
+        };
+        let next = match target {
+            Some(label) => self.dialog.label_line(label),
+            None => Some(self.current_line + 1),
+        };
+        self.go_to(next, player)
+    }
+
+    /// 현재 줄의 행동을 실행하지 못했을 때. @FAIL이 있으면 그 라벨로, 없으면 advance와 같다
+    pub fn advance_failed(&mut self, player: &Player) -> bool {
+        let Some(label) = self.current().and_then(|l| l.fail.clone()) else {
+            return self.advance(player);
+        };
+        let next = self.dialog.label_line(&label);
+        self.go_to(next, player)
+    }
+
+    fn go_to(&mut self, next: Option<usize>, player: &Player) -> bool {
+        self.selected_choice = 0;
+        match next {
+            Some(next) => {
+                self.current_line = next;
+                self.skip_hidden(player)
+            }
+            None => false,
         }
     }
 
-    pub fn current_action(&self) -> Option<&crate::data::DialogAction> {
-        self.lines
-            .get(self.current_line)
-            .and_then(|l| l.action.as_ref())
+    fn skip_hidden(&mut self, player: &Player) -> bool {
+        while let Some(line) = self.current() {
+            if player.check_conditions(&line.conditions) {
+                return true;
+            }
+            self.current_line += 1;
+        }
+        false
     }
 }
 
+/// 한 번에 사고팔 수 있는 최대 수량
+pub const MAX_QUANTITY: u32 = 99;
+
 #[derive(Debug, Clone)]
 pub struct ShopState {
     pub shop: Shop,
     pub items: Vec<Item>,
     pub selected: usize,
     pub mode: ShopMode,
+    pub step: ShopStep,
+    pub quantity: u32,
+    /// (안내, 성공 여부). 품절이나 골드 부족 같은 실패와 거래 결과를 보여 주고 다음 입력에서 사라진다
+    pub message: Option<(String, bool)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -65,6 +148,14 @@ pub enum ShopMode {
     Select,
 }
 
+/// 사고팔 아이템을 고른 뒤 수량을 정하고 확인받는 단계
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShopStep {
+    List,
+    Quantity,
+    Confirm,
+}
+
 impl ShopState {
     pub fn new(shop: Shop, items: Vec<Item>) -> Self {
         Self {
@@ -72,9 +163,54 @@ impl ShopState {
             items,
             selected: 0,
             mode: ShopMode::Select,
+            step: ShopStep::List,
+            quantity: 1,
+            message: None,
+        }
+    }
+
+    /// 수량 선택에서 고를 수 있는 최대 수. 재고, 골드, 가진 개수 중 작은 쪽
+    pub fn max_quantity(&self, player: &Player, stock: &ShopStock) -> u32 {
+        match self.mode {
+            ShopMode::Buy => {
+                let Some(item) = self.items.get(self.selected) else {
+                    return 0;
+                };
+                let price = self.shop.buy_price(item);
+                let affordable = if price > 0 {
+                    (player.stats.gold / price).max(0) as u32
+                } else {
+                    MAX_QUANTITY
+                };
+                let in_stock = stock
+                    .remaining(&self.shop, &item.id)
+                    .unwrap_or(MAX_QUANTITY);
+                affordable.min(in_stock).min(MAX_QUANTITY)
+            }
+            ShopMode::Sell => player
+                .inventory()
+                .get(self.selected)
+                .map_or(0, |e| e.count.min(MAX_QUANTITY)),
+            ShopMode::Select => 0,
         }
     }
 
+    /// 고른 수량만큼의 총액
+    pub fn total_price(&self, player: &Player) -> i32 {
+        let price = match self.mode {
+            ShopMode::Buy => self
+                .items
+                .get(self.selected)
+                .map(|i| self.shop.buy_price(i)),
+            ShopMode::Sell => player
+                .inventory()
+                .get(self.selected)
+                .map(|e| self.shop.sell_price(&e.item)),
+            ShopMode::Select => None,
+        };
+        price.unwrap_or(0) * self.quantity as i32
+    }
+
     pub fn move_up(&mut self) {
         if self.selected > 0 {
             self.selected -= 1;
@@ -96,7 +232,7 @@ pub struct MenuState {
 
 impl MenuState {
     pub fn menu_count(&self) -> usize {
-        if self.has_save { 3 } else { 2 }
+        if self.has_save { 4 } else { 3 }
     }
 
     pub fn move_up(&mut self) {
@@ -111,3 +247,160 @@ impl MenuState {
         }
     }
 }
+
+#[derive(Debug, Clone, Default)]
+pub struct ContentErrorState {
+    pub scroll: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotMode {
+    Load,
+    Save,
+}
+
+/// 저장 슬롯 선택 화면. confirm은 덮어쓰기 확인 대기 중인지 여부
+#[derive(Debug, Clone)]
+pub struct SlotSelectState {
+    pub mode: SlotMode,
+    pub selected: usize,
+    pub summaries: Vec<Option<SaveSummary>>,
+    pub confirm: bool,
+    pub quit_after_save: bool, // 저장하면 메뉴로 나간다
+}
+
+impl SlotSelectState {
+    pub fn new(mode: SlotMode, summaries: Vec<Option<SaveSummary>>, selected: usize) -> Self {
+        Self {
+            mode,
+            selected: selected.min(summaries.len().saturating_sub(1)),
+            summaries,
+            confirm: false,
+            quit_after_save: false,
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.summaries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_summary(&self) -> Option<&SaveSummary> {
+        self.summaries.get(self.selected).and_then(|s| s.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DialogAction, parse_dialogs, try_parse_dialogs};
+
+    const DIALOG: &str = "@DIALOG:d\n\
+        HAS_QUEST=q::벌써 받았구나.\n\
+        @JUMP:END\n\
+        부탁 하나 들어주겠나?\n\
+        @CHOICE:yes:네\n\
+        @CHOICE:no:아니요\n\
+        @LABEL:yes\n\
+        GIVE_QUEST=q:고맙네.\n\
+        @JUMP:END\n\
+        @LABEL:no\n\
+        아쉽군.\n\
+        @END\n";
+
+    #[test]
+    fn choices_pick_branch_and_conditions_are_live() {
+        let dialog = &parse_dialogs(DIALOG)[0];
+        let mut player = Player::new("Hero".into(), "village");
+
+        let mut state = DialogState::new("NPC".into(), dialog, &player).unwrap();
+        assert_eq!(state.choices().len(), 2);
+        state.move_choice_down();
+        assert!(state.advance(&player));
+        assert_eq!(state.current_text(), Some("아쉽군."));
+        assert!(state.current_actions().is_empty());
+        assert!(!state.advance(&player));
+
+        let mut state = DialogState::new("NPC".into(), dialog, &player).unwrap();
+        assert!(state.advance(&player));
+        assert!(matches!(
+            state.current_actions(),
+            [DialogAction::GiveQuest(id)] if id == "q"
+        ));
+        assert!(state.is_last_line());
+
+        player.add_quest("q");
+        let state = DialogState::new("NPC".into(), dialog, &player).unwrap();
+        assert_eq!(state.current_text(), Some("벌써 받았구나."));
+    }
+
+    #[test]
+    fn unknown_labels_are_reported() {
+        let errors =
+            try_parse_dialogs("@DIALOG:d\n안녕\n@CHOICE:nowhere:가자\n@END\n").unwrap_err();
+        assert_eq!(errors[0].line, 3);
+    }
+
+    #[test]
+    fn failed_actions_follow_fail_label() {
+        let dialog = &parse_dialogs(
+            "@DIALOG:d\nREST=10:묵어 가시겠어요?\n@FAIL:poor\n쉬었다.\n@JUMP:END\n\
+             @LABEL:poor\n돈이 모자라요.\n@END\n",
+        )[0];
+        let player = Player::new("Hero".into(), "village");
+
+        let mut state = DialogState::new("NPC".into(), dialog, &player).unwrap();
+        assert!(state.advance_failed(&player));
+        assert_eq!(state.current_text(), Some("돈이 모자라요."));
+
+        let mut state = DialogState::new("NPC".into(), dialog, &player).unwrap();
+        assert!(state.advance(&player));
+        assert_eq!(state.current_text(), Some("쉬었다."));
+    }
+
+    #[test]
+    fn inn_charges_by_level_or_turns_the_player_away() {
+        let dialogs = parse_dialogs(include_str!("../../resources/data/dialogs.dat"));
+        let inn = dialogs.iter().find(|d| d.id == "dialog_inn").unwrap();
+        let mut player = Player::new("Hero".into(), "village");
+        player.stats.level = 3;
+
+        // 첫 선택지(쉬어 간다)를 고르면 REST 줄로 간다
+        let rest_line = |player: &Player| {
+            let mut state = DialogState::new("NPC".into(), inn, player).unwrap();
+            assert!(state.advance(player));
+            let [DialogAction::Rest(price)] = state.current_actions() else {
+                panic!("expected REST");
+            };
+            (state.clone(), *price)
+        };
+
+        player.stats.gold = 29;
+        let (mut state, price) = rest_line(&player);
+        assert_eq!(player.rest_price(price), 30);
+        assert!(!player.pay_for_rest(price));
+        assert_eq!(player.stats.gold, 29);
+        assert!(state.advance_failed(&player));
+        assert_eq!(
+            state.current_text(),
+            Some("돈이 모자라시네요. 다음에 또 들러 주세요.")
+        );
+
+        player.stats.gold = 50;
+        let (mut state, price) = rest_line(&player);
+        assert!(player.pay_for_rest(price));
+        assert_eq!(player.stats.gold, 20);
+        assert!(state.advance(&player));
+        assert_eq!(
+            state.current_text(),
+            Some("푹 쉬셨나요? 한결 가벼워 보이시네요.")
+        );
+    }
+}
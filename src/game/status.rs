@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+
+use crate::data::{StatusEffect, StatusKind};
+
+/// 독 피해와 재생 회복이 들어가는 간격(틱)
+pub const STATUS_PULSE: u32 = 30;
+
+/// 플레이어나 적에게 걸려 있는 상태 효과. 종류마다 하나씩만 걸린다
+#[derive(Debug, Clone, Default)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// 같은 종류는 새 효과로 바꾼다. 지속 틱이 0이면 그 종류를 없앤다
+    pub fn apply(&mut self, effect: StatusEffect) {
+        self.effects.retain(|e| e.kind != effect.kind);
+        if effect.ticks > 0 {
+            self.effects.push(effect);
+        }
+    }
+
+    /// 걸려 있지 않으면 0
+    pub fn power(&self, kind: StatusKind) -> i32 {
+        self.effects
+            .iter()
+            .find(|e| e.kind == kind)
+            .map_or(0, |e| e.power)
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.effects.iter().any(|e| e.kind == StatusKind::Stun)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    /// 한 틱 진행한다. pulse면 독 피해와 재생 회복을 더한 체력 변화를 돌려준다
+    pub fn tick(&mut self, pulse: bool) -> i32 {
+        let mut hp_change = 0;
+        for effect in &mut self.effects {
+            if pulse {
+                match effect.kind {
+                    StatusKind::Poison => hp_change -= effect.power,
+                    StatusKind::Regen => hp_change += effect.power,
+                    StatusKind::Stun | StatusKind::AtkUp | StatusKind::DefUp => {}
+                }
+            }
+            effect.ticks -= 1;
+        }
+        self.effects.retain(|e| e.ticks > 0);
+        hp_change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, power: i32, ticks: u32) -> StatusEffect {
+        StatusEffect { kind, power, ticks }
+    }
+
+    #[test]
+    fn effects_replace_tick_and_expire() {
+        let mut status = StatusEffects::default();
+        status.apply(effect(StatusKind::Poison, 2, 3));
+        status.apply(effect(StatusKind::Poison, 5, 2));
+        status.apply(effect(StatusKind::Regen, 1, 10));
+
+        assert_eq!(status.power(StatusKind::Poison), 5);
+        assert_eq!(status.tick(true), -4);
+        assert_eq!(status.tick(false), 0);
+        assert_eq!(status.power(StatusKind::Poison), 0);
+        assert_eq!(status.tick(true), 1);
+
+        // 지속 0은 해독
+        status.apply(effect(StatusKind::Regen, 0, 0));
+        assert_eq!(status.iter().count(), 0);
+    }
+}
@@ -5,6 +5,7 @@ extern crate alloc;
 mod data;
 mod game;
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::str;
@@ -12,28 +13,52 @@ use core::str;
 use wipi::{app::App, event::KeyCode, framebuffer::Framebuffer, resource::Resource, wipi_main};
 
 use data::{
-    Dialog, Enemy, Item, Map, Npc, Quest, Shop, parse_dialogs, parse_enemies, parse_items,
-    parse_maps, parse_npcs, parse_quests, parse_shops,
+    Boss, Dialog, Enemy, Item, LevelGrowth, LevelUp, LootSource, LootTable, Map, MapTrigger, Npc,
+    ParseError, Quest, Shop, Skill, Tile, TriggerAction, parse_bosses, parse_dialogs,
+    parse_enemies, parse_items, parse_levels, parse_loot, parse_maps, parse_npcs, parse_quests,
+    parse_shops, parse_skills, try_parse_bosses, try_parse_dialogs, try_parse_enemies,
+    try_parse_items, try_parse_levels, try_parse_loot, try_parse_maps, try_parse_npcs,
+    try_parse_quests, try_parse_shops, try_parse_skills,
 };
 use game::{
-    COLOR_DARK_GRAY, COLOR_RED, COLOR_WHITE, CombatSystem, DialogState, GameState, InventoryState,
-    MenuState, Player, ShopMode, ShopState, TileEvent, check_tile_event, clear_screen, draw_dialog,
-    draw_explore, draw_inventory, draw_menu, draw_quest_log, draw_rect, draw_shop, draw_stats,
-    draw_text, fill_rect, has_save_data, load_game, save_game,
+    COLOR_DARK_GRAY, COLOR_RED, COLOR_WHITE, CombatSystem, ContentError, ContentErrorState,
+    DialogState, FADE_STEPS, GameState, InventoryState, ItemAction, KillReward, MenuState, Player,
+    Rng, ShopMode, ShopState, ShopStep, ShopStock, SlotMode, SlotSelectState, TileEvent,
+    check_tile_event, clear_screen, draw_content_errors, draw_dialog, draw_explore, draw_fade,
+    draw_inventory, draw_level_up, draw_menu, draw_notice, draw_quest_log, draw_rect, draw_shop,
+    draw_slot_select, draw_stats, draw_text, fill_rect, has_save_data, load_game,
+    read_save_summaries, save_game,
 };
 
+const NOTICE_TICKS: u32 = 60;
+const LEVEL_UP_TICKS: u32 = 150;
+/// 여관에서 쉴 때 화면이 어두워졌다 밝아지는 시간. 가장 어두울 때 회복하고 저장한다
+const REST_FADE_TICKS: u32 = 60;
+
 pub struct RpgGame {
     state: GameState,
     player: Player,
     items: Vec<Item>,
     enemies: Vec<Enemy>,
+    bosses: Vec<Boss>,
     maps: Vec<Map>,
     npcs: Vec<Npc>,
     dialogs: Vec<Dialog>,
     quests: Vec<Quest>,
     shops: Vec<Shop>,
+    shop_stock: ShopStock,
+    loot_tables: Vec<LootTable>,
+    skills: Vec<Skill>,
+    levels: Vec<LevelGrowth>,
     inventory_state: InventoryState,
     combat: CombatSystem,
+    rng: Rng,
+    current_slot: Option<usize>, // 이 판을 저장하는 슬롯. 새 게임은 처음 저장할 때 고른다
+    ticks: u32,
+    notice: Option<(String, u32)>,
+    level_up: Option<(LevelUp, u32)>,
+    rest_fade: Option<u32>,
+    content_errors: Vec<ContentError>,
 }
 
 impl Default for RpgGame {
@@ -44,95 +69,126 @@ impl Default for RpgGame {
 
 impl RpgGame {
     pub fn new() -> Self {
-        let items = Self::load_items();
-        let enemies = Self::load_enemies();
-        let maps = Self::load_maps();
-        let npcs = Self::load_npcs();
-        let dialogs = Self::load_dialogs();
-        let quests = Self::load_quests();
-        let shops = Self::load_shops();
-
-        let menu_state = MenuState {
-            selected: 0,
-            has_save: has_save_data(),
+        let mut content_errors = Vec::new();
+        let items = Self::load_data(
+            "items.dat",
+            try_parse_items,
+            parse_items,
+            &mut content_errors,
+        );
+        let enemies = Self::load_data(
+            "enemies.dat",
+            try_parse_enemies,
+            parse_enemies,
+            &mut content_errors,
+        );
+        let bosses = Self::load_data(
+            "bosses.dat",
+            try_parse_bosses,
+            parse_bosses,
+            &mut content_errors,
+        );
+        let maps = Self::load_data("maps.dat", try_parse_maps, parse_maps, &mut content_errors);
+        let npcs = Self::load_data("npcs.dat", try_parse_npcs, parse_npcs, &mut content_errors);
+        let dialogs = Self::load_data(
+            "dialogs.dat",
+            try_parse_dialogs,
+            parse_dialogs,
+            &mut content_errors,
+        );
+        let quests = Self::load_data(
+            "quests.dat",
+            try_parse_quests,
+            parse_quests,
+            &mut content_errors,
+        );
+        let shops = Self::load_data(
+            "shops.dat",
+            try_parse_shops,
+            parse_shops,
+            &mut content_errors,
+        );
+
+        let loot_tables =
+            Self::load_data("loot.dat", try_parse_loot, parse_loot, &mut content_errors);
+        let skills = Self::load_data(
+            "skills.dat",
+            try_parse_skills,
+            parse_skills,
+            &mut content_errors,
+        );
+        let levels = Self::load_data(
+            "levels.dat",
+            try_parse_levels,
+            parse_levels,
+            &mut content_errors,
+        );
+
+        let state = if content_errors.is_empty() {
+            GameState::Menu(MenuState {
+                selected: 0,
+                has_save: has_save_data(),
+            })
+        } else {
+            GameState::ContentError(ContentErrorState::default())
         };
 
         Self {
-            state: GameState::Menu(menu_state),
+            state,
             player: Player::new(String::from("Hero"), "village"),
             items,
             enemies,
+            bosses,
             maps,
             npcs,
             dialogs,
             quests,
             shops,
+            shop_stock: ShopStock::default(),
+            loot_tables,
+            skills,
+            levels,
             inventory_state: InventoryState::default(),
             combat: CombatSystem::new(),
+            rng: Rng::new(0),
+            current_slot: None,
+            ticks: 0,
+            notice: None,
+            level_up: None,
+            rest_fade: None,
+            content_errors,
         }
     }
 
-    fn load_items() -> Vec<Item> {
-        if let Ok(resource) = Resource::new("data/items.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_items(text);
-        }
-        Vec::new()
-    }
-
-    fn load_enemies() -> Vec<Enemy> {
-        if let Ok(resource) = Resource::new("data/enemies.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_enemies(text);
-        }
-        Vec::new()
-    }
-
-    fn load_maps() -> Vec<Map> {
-        if let Ok(resource) = Resource::new("data/maps.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_maps(text);
-        }
-        Vec::new()
-    }
-
-    fn load_npcs() -> Vec<Npc> {
-        if let Ok(resource) = Resource::new("data/npcs.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_npcs(text);
-        }
-        Vec::new()
-    }
-
-    fn load_dialogs() -> Vec<Dialog> {
-        if let Ok(resource) = Resource::new("data/dialogs.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_dialogs(text);
-        }
-        Vec::new()
-    }
-
-    fn load_quests() -> Vec<Quest> {
-        if let Ok(resource) = Resource::new("data/quests.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_quests(text);
-        }
-        Vec::new()
-    }
+    fn load_data<T>(
+        file: &'static str,
+        strict: fn(&str) -> Result<Vec<T>, Vec<ParseError>>,
+        lenient: fn(&str) -> Vec<T>,
+        errors: &mut Vec<ContentError>,
+    ) -> Vec<T> {
+        let Ok(resource) = Resource::new(&format!("data/{}", file)) else {
+            return Vec::new();
+        };
+        let Ok(text) = str::from_utf8(resource.read()) else {
+            return Vec::new();
+        };
 
-    fn load_shops() -> Vec<Shop> {
-        if let Ok(resource) = Resource::new("data/shops.dat")
-            && let Ok(text) = str::from_utf8(resource.read())
-        {
-            return parse_shops(text);
+        // 시뮬레이션 빌드에서는 잘못된 데이터로 실행하지 않고 오류 화면을 띄운다
+        if cfg!(feature = "simulation") {
+            match strict(text) {
+                Ok(values) => values,
+                Err(parse_errors) => {
+                    errors.extend(
+                        parse_errors
+                            .into_iter()
+                            .map(|error| ContentError { file, error }),
+                    );
+                    lenient(text)
+                }
+            }
+        } else {
+            lenient(text)
         }
-        Vec::new()
     }
 
     fn current_map(&self) -> Option<&Map> {
@@ -141,44 +197,53 @@ impl RpgGame {
             .find(|m| m.id == self.player.current_map_id)
     }
 
-    fn start_new_game(&mut self) {
+    /// hardcore면 쓰러졌을 때 되살아나지 않는다
+    fn start_new_game(&mut self, hardcore: bool) {
+        // 메뉴에서 보낸 프레임 수를 시드로 사용한다
+        self.rng = Rng::new(self.ticks as u64);
         self.player = Player::new(String::from("Hero"), "village");
+        self.player.hardcore = hardcore;
+        self.current_slot = None;
+        self.player.stats.update_exp_to_next(&self.levels);
+        self.level_up = None;
 
         if let Some(sword) = self.items.iter().find(|i| i.id == "wooden_sword").cloned() {
-            self.player.add_item(sword);
-            self.player.equipped_weapon = Some(0);
+            let uid = self.player.add_item(sword);
+            self.player.equip(uid);
         }
         if let Some(armor) = self.items.iter().find(|i| i.id == "cloth").cloned() {
-            self.player.add_item(armor);
-            self.player.equipped_armor = Some(1);
+            let uid = self.player.add_item(armor);
+            self.player.equip(uid);
         }
         if let Some(potion) = self.items.iter().find(|i| i.id == "potion").cloned() {
-            self.player.add_item(potion.clone());
-            self.player.add_item(potion);
+            self.player.add_items(potion, 2);
         }
 
-        if let Some(map) = self.maps.iter().find(|m| m.id == "village") {
-            self.player.spawn_at_map(map);
-            self.combat.spawn_enemies(map, &self.enemies);
+        if let Some(map) = self.maps.iter().find(|m| m.id == "village").cloned() {
+            self.player.spawn_at_map(&map, None);
+            self.player.set_respawn_here();
+            self.spawn_map_enemies(&map);
         }
 
         self.state = GameState::Explore;
     }
 
-    fn continue_game(&mut self) {
-        self.player = Player::new(String::from("Hero"), "village");
-
-        if load_game(&mut self.player) {
-            if let Some(map) = self
-                .maps
-                .iter()
-                .find(|m| m.id == self.player.current_map_id)
-            {
-                self.combat.spawn_enemies(map, &self.enemies);
+    fn continue_game(&mut self, slot: usize) {
+        if load_game(slot, &mut self.player, &mut self.rng, &self.items).is_ok() {
+            self.current_slot = Some(slot);
+            self.player.stats.update_exp_to_next(&self.levels);
+            self.level_up = None;
+            if let Some(map) = self.current_map().cloned() {
+                self.spawn_map_enemies(&map);
             }
             self.state = GameState::Explore;
         } else {
-            self.start_new_game();
+            // 읽을 수 없는 슬롯은 요약을 다시 읽어 손상 표시를 보여준다
+            self.state = GameState::SlotSelect(SlotSelectState::new(
+                SlotMode::Load,
+                read_save_summaries(),
+                slot,
+            ));
         }
     }
 
@@ -190,20 +255,28 @@ impl RpgGame {
                 KeyCode::Ok => {
                     let action = if menu.has_save {
                         match menu.selected {
-                            0 => MenuAction::NewGame,
-                            1 => MenuAction::Continue,
+                            0 => MenuAction::NewGame { hardcore: false },
+                            1 => MenuAction::NewGame { hardcore: true },
+                            2 => MenuAction::Continue,
                             _ => MenuAction::Exit,
                         }
                     } else {
                         match menu.selected {
-                            0 => MenuAction::NewGame,
+                            0 => MenuAction::NewGame { hardcore: false },
+                            1 => MenuAction::NewGame { hardcore: true },
                             _ => MenuAction::Exit,
                         }
                     };
 
                     match action {
-                        MenuAction::NewGame => self.start_new_game(),
-                        MenuAction::Continue => self.continue_game(),
+                        MenuAction::NewGame { hardcore } => self.start_new_game(hardcore),
+                        MenuAction::Continue => {
+                            self.state = GameState::SlotSelect(SlotSelectState::new(
+                                SlotMode::Load,
+                                read_save_summaries(),
+                                self.current_slot.unwrap_or(0),
+                            ));
+                        }
                         MenuAction::Exit => {
                             wipi::kernel::exit(0);
                         }
@@ -215,6 +288,25 @@ impl RpgGame {
     }
 
     fn handle_explore_input(&mut self, key: KeyCode) {
+        // 기절한 동안에는 움직이거나 공격할 수 없다. 메뉴 키는 그대로 쓴다
+        let action = matches!(
+            key,
+            KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Ok
+                | KeyCode::Key4
+                | KeyCode::Key5
+                | KeyCode::Key6
+                | KeyCode::Key7
+                | KeyCode::Key8
+                | KeyCode::Key9
+        );
+        if action && self.player.status.is_stunned() {
+            return;
+        }
+
         let (dx, dy) = match key {
             KeyCode::Up => (0, -1),
             KeyCode::Down => (0, 1),
@@ -229,16 +321,22 @@ impl RpgGame {
                 let reward = self.combat.player_attack(
                     self.player.x,
                     self.player.y,
-                    self.player.total_atk(),
+                    self.player.combat_atk(),
+                    self.player.crit_chance(),
                     self.player.facing,
+                    &mut self.rng,
                 );
                 if let Some(reward) = reward {
-                    self.player.stats.add_exp(reward.exp);
-                    self.player.stats.gold += reward.gold;
-                    self.update_kill_quest(&reward.enemy_id);
+                    self.grant_kill_reward(reward);
                 }
                 return;
             }
+            KeyCode::Key4 => return self.cast_skill(0),
+            KeyCode::Key5 => return self.cast_skill(1),
+            KeyCode::Key6 => return self.cast_skill(2),
+            KeyCode::Key7 => return self.cast_skill(3),
+            KeyCode::Key8 => return self.cast_skill(4),
+            KeyCode::Key9 => return self.cast_skill(5),
             KeyCode::Key1 => {
                 self.inventory_state = InventoryState::default();
                 self.state = GameState::Inventory;
@@ -253,15 +351,18 @@ impl RpgGame {
                 return;
             }
             KeyCode::Key0 => {
-                save_game(&self.player);
+                self.open_save_picker(false);
                 return;
             }
             KeyCode::Back => {
-                save_game(&self.player);
-                self.state = GameState::Menu(MenuState {
-                    selected: 0,
-                    has_save: has_save_data(),
-                });
+                // 슬롯이 정해지지 않은 판은 빈 슬롯을 덮어쓰지 않도록 직접 고르게 한다
+                match self.current_slot {
+                    Some(slot) => {
+                        self.save_to_slot(slot);
+                        self.return_to_menu();
+                    }
+                    None => self.open_save_picker(true),
+                }
                 return;
             }
             _ => return,
@@ -273,34 +374,171 @@ impl RpgGame {
             let new_x = (self.player.x as i32 + dx) as usize;
             let new_y = (self.player.y as i32 + dy) as usize;
 
+            // 보스가 살아 있는 동안에는 출구로 나갈 수 없다
+            let leaving = matches!(map.get_tile(new_x, new_y), Tile::Exit | Tile::Dungeon);
+            if leaving && let Some(boss) = self.combat.boss() {
+                self.notice = Some((format!("{} blocks the way!", boss.data.name), NOTICE_TICKS));
+                return;
+            }
+
             if self.player.can_move(map, dx, dy) && !self.combat.enemy_at(new_x, new_y) {
                 self.player.move_by(dx, dy);
                 self.check_tile_events();
+                self.update_reach_quests();
             }
         }
     }
 
+    fn grant_kill_reward(&mut self, reward: KillReward) {
+        self.player.stats.gold += reward.gold;
+        self.update_kill_quest(&reward.enemy_id);
+        self.roll_loot(LootSource::Enemy, &reward.enemy_id);
+
+        if let Some(index) = reward.patrol {
+            let map_id = self.player.current_map_id.clone();
+            self.player.defeat_patrol(&map_id, index);
+        }
+        if reward.boss {
+            self.player.defeat_boss(&reward.enemy_id);
+            if let Some(enemy) = self.enemies.iter().find(|e| e.id == reward.enemy_id) {
+                self.notice = Some((format!("Defeated {}!", enemy.name), NOTICE_TICKS));
+            }
+        }
+
+        // 새로 배운 스킬 알림이 다른 알림보다 앞서도록 마지막에 준다
+        self.gain_exp(reward.exp);
+    }
+
+    /// 경험치를 얻는다. 레벨이 오르면 레벨업 창을 띄우고, 새로 배운 스킬을 알린다
+    fn gain_exp(&mut self, exp: i32) {
+        let known_before: Vec<String> = self
+            .player
+            .known_skills(&self.skills)
+            .iter()
+            .map(|s| s.id.clone())
+            .collect();
+
+        let Some(up) = self.player.stats.add_exp(exp, &self.levels) else {
+            return;
+        };
+        self.show_level_up(up);
+
+        let known = self.player.known_skills(&self.skills);
+        if let Some(slot) = known.iter().position(|s| !known_before.contains(&s.id)) {
+            self.notice = Some((
+                format!("Learned {} (Key {})", known[slot].name, slot + 4),
+                NOTICE_TICKS,
+            ));
+        }
+    }
+
+    /// 레벨업 창을 띄운다. 창이 떠 있는 동안 또 오르면 하나로 합친다
+    fn show_level_up(&mut self, up: LevelUp) {
+        let up = match self.level_up.take() {
+            Some((prev, _)) => prev.then(up),
+            None => up,
+        };
+        self.level_up = Some((up, LEVEL_UP_TICKS));
+    }
+
+    fn cast_skill(&mut self, slot: usize) {
+        let Some(skill) = self.player.known_skills(&self.skills).get(slot).copied() else {
+            return;
+        };
+
+        if self.player.stats.current_mp < skill.mp_cost {
+            self.notice = Some((String::from("Not enough MP"), NOTICE_TICKS));
+            return;
+        }
+
+        let outcome = self.combat.cast_skill(
+            skill,
+            self.player.x,
+            self.player.y,
+            self.player.combat_atk(),
+            self.player.facing,
+            &mut self.rng,
+        );
+        let Some(outcome) = outcome else {
+            return;
+        };
+
+        self.player.stats.current_mp -= skill.mp_cost;
+        self.player.stats.heal(outcome.heal);
+        for effect in outcome.status {
+            self.player.status.apply(effect);
+        }
+        for reward in outcome.kills {
+            self.grant_kill_reward(reward);
+        }
+    }
+
     fn update_combat(&mut self) {
-        if !matches!(self.state, GameState::Explore) {
+        // 여관에서 쉬는 동안에는 적이 움직이지 않는다
+        if !matches!(self.state, GameState::Explore) || self.rest_fade.is_some() {
             return;
         }
 
         if let Some(map) = self.current_map().cloned() {
-            let result =
-                self.combat
-                    .update(self.player.x, self.player.y, self.player.total_def(), &map);
+            let result = self
+                .combat
+                .update(&mut self.player, &map, &self.enemies, &mut self.rng);
+
+            if let Some(name) = result.enraged {
+                self.notice = Some((format!("{} is enraged!", name), NOTICE_TICKS));
+            }
+            self.player.stats.heal(result.healed);
+            for reward in result.kills {
+                self.grant_kill_reward(reward);
+            }
 
             if result.damage_taken > 0 {
                 self.player.stats.take_damage(result.damage_taken);
 
                 if self.player.stats.is_dead() {
-                    self.state = GameState::GameOver;
+                    self.handle_death();
                 }
             }
         }
     }
 
+    /// 하드코어면 게임이 끝난다. 아니면 골드를 잃고 마지막으로 들른 마을이나 치료사 앞에서 되살아난다
+    fn handle_death(&mut self) {
+        let Some(lost) = self.player.fall() else {
+            // 끝난 판으로 저장해 두어 이어하기로 되돌릴 수 없게 한다
+            if let Some(slot) = self.current_slot {
+                self.save_to_slot(slot);
+            }
+            self.current_slot = None;
+            self.state = GameState::GameOver;
+            return;
+        };
+
+        let target = self
+            .player
+            .respawn_target(&self.maps)
+            .map(|(map, point)| (map.clone(), point));
+        if let Some((map, point)) = target {
+            self.player.spawn_at_map(&map, None);
+            if let Some((x, y)) = point {
+                self.player.x = x;
+                self.player.y = y;
+            }
+            self.spawn_map_enemies(&map);
+        }
+
+        self.notice = Some((format!("You fell... Lost {}G", lost), NOTICE_TICKS));
+        // 다시 불러와 죽음을 되돌리지 못하도록 벌칙을 바로 저장한다
+        if let Some(slot) = self.current_slot {
+            self.save_to_slot(slot);
+        }
+    }
+
     fn check_tile_events(&mut self) {
+        if self.fire_triggers() {
+            return;
+        }
+
         let event = if let Some(map) = self.current_map() {
             check_tile_event(map, &self.player)
         } else {
@@ -309,9 +547,9 @@ impl RpgGame {
 
         if let Some(event) = event {
             match event {
-                TileEvent::MapExit(target) => {
-                    if !target.is_empty() {
-                        self.change_map(&target);
+                TileEvent::MapExit(link) | TileEvent::DungeonEntrance(link) => {
+                    if !link.target.is_empty() {
+                        self.change_map(&link.target, link.spawn.as_deref());
                     }
                 }
                 TileEvent::Treasure => {
@@ -320,40 +558,180 @@ impl RpgGame {
                         .player
                         .is_treasure_opened(&map_id, self.player.x, self.player.y)
                     {
-                        if let Some(potion) = self.items.iter().find(|i| i.id == "potion").cloned()
-                        {
-                            self.player.add_item(potion);
-                        }
+                        let loot_id = self
+                            .current_map()
+                            .map(|m| m.treasure_loot_id(self.player.x, self.player.y).into())
+                            .unwrap_or_else(|| String::from("default"));
+                        self.roll_loot(LootSource::Treasure, &loot_id);
                         self.player
                             .open_treasure(&map_id, self.player.x, self.player.y);
                     }
                 }
                 TileEvent::Npc => {}
-                TileEvent::DungeonEntrance(target) => {
-                    if !target.is_empty() {
-                        self.change_map(&target);
-                    }
+            }
+        }
+    }
+
+    /// 밟은 칸의 트리거를 발동한다. 맵이나 화면이 바뀌면 true
+    fn fire_triggers(&mut self) -> bool {
+        let (x, y) = (self.player.x, self.player.y);
+        let Some(map) = self.current_map() else {
+            return false;
+        };
+        let map_id = map.id.clone();
+        let triggers: Vec<(usize, MapTrigger)> = map
+            .triggers
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.x == x && t.y == y)
+            .map(|(i, t)| (i, t.clone()))
+            .collect();
+
+        for (index, trigger) in triggers {
+            if trigger.once && self.player.is_trigger_fired(&map_id, index) {
+                continue;
+            }
+            if !self.player.check_conditions(&trigger.conditions) {
+                continue;
+            }
+            let scripts: Vec<_> = trigger
+                .actions
+                .iter()
+                .filter_map(|action| match action {
+                    TriggerAction::Script(action) => Some(action.clone()),
+                    _ => None,
+                })
+                .collect();
+            if !self.can_apply_dialog_actions(&scripts) {
+                continue;
+            }
+
+            if trigger.once {
+                self.player.mark_trigger_fired(&map_id, index);
+            }
+            for action in trigger.actions {
+                self.apply_trigger_action(action);
+            }
+
+            let moved =
+                self.player.current_map_id != map_id || (self.player.x, self.player.y) != (x, y);
+            if moved || !matches!(self.state, GameState::Explore) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn apply_trigger_action(&mut self, action: TriggerAction) {
+        match action {
+            TriggerAction::Dialog(id) => {
+                // 트리거 대화는 말하는 사람 없이 보여준다
+                let state = self
+                    .dialogs
+                    .iter()
+                    .find(|d| d.id == id)
+                    .and_then(|dialog| DialogState::new(String::new(), dialog, &self.player));
+                if let Some(state) = state {
+                    self.state = GameState::Dialog(state);
+                }
+            }
+            TriggerAction::Teleport(map_id, pos) => {
+                if map_id != self.player.current_map_id {
+                    self.change_map(&map_id, None);
+                }
+                if let Some((x, y)) = pos {
+                    self.player.x = x;
+                    self.player.y = y;
+                    self.update_reach_quests();
+                }
+            }
+            TriggerAction::Boss(enemy_id, pos) => {
+                if let Some((x, y)) = pos.or_else(|| self.free_tile_near_player()) {
+                    self.spawn_boss(&enemy_id, x, y);
                 }
             }
+            TriggerAction::Script(action) => self.apply_dialog_action(action),
         }
     }
 
-    fn change_map(&mut self, target_id: &str) {
+    /// 바라보는 칸을 먼저, 막혀 있으면 나머지 이웃 칸 중 적이 없고 지나갈 수 있는 칸
+    fn free_tile_near_player(&self) -> Option<(usize, usize)> {
+        let map = self.current_map()?;
+        let (x, y) = (self.player.x, self.player.y);
+        [
+            self.player.facing.apply(x, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+        ]
+        .into_iter()
+        .find(|&(tx, ty)| {
+            (tx, ty) != (x, y)
+                && map.get_tile(tx, ty).is_passable()
+                && !self.combat.enemy_at(tx, ty)
+        })
+    }
+
+    fn roll_loot(&mut self, source: LootSource, id: &str) {
+        let Some(table) = self
+            .loot_tables
+            .iter()
+            .find(|t| t.source == source && t.id == id)
+        else {
+            return;
+        };
+
+        if let Some(Some(item_id)) = self.rng.pick_weighted(&table.entries)
+            && let Some(item) = self.items.iter().find(|i| i.id == *item_id).cloned()
+        {
+            self.notice = Some((format!("Obtained {}", item.name), NOTICE_TICKS));
+            self.player.add_item(item);
+            self.update_collect_quests();
+        }
+    }
+
+    fn spawn_map_enemies(&mut self, map: &Map) {
+        self.combat
+            .spawn_enemies(map, &self.enemies, &self.player, &mut self.rng);
+        for (x, y, enemy_id) in &map.bosses {
+            self.spawn_boss(enemy_id, *x, *y);
+        }
+    }
+
+    /// 쓰러뜨린 보스는 다시 나오지 않는다. bosses.dat에 없으면 일반 적으로 나온다
+    fn spawn_boss(&mut self, enemy_id: &str, x: usize, y: usize) {
+        if self.player.is_boss_defeated(enemy_id) {
+            return;
+        }
+        let Some(data) = self.enemies.iter().find(|e| e.id == enemy_id) else {
+            return;
+        };
+        match self.bosses.iter().find(|b| b.enemy_id == enemy_id) {
+            Some(boss) => self.combat.spawn_boss(data, boss, x, y),
+            None => self.combat.spawn_enemy(data, x, y),
+        }
+    }
+
+    fn change_map(&mut self, target_id: &str, spawn: Option<&str>) {
         let map = self.maps.iter().find(|m| m.id == target_id).cloned();
         if let Some(map) = map {
-            self.player.current_map_id = map.id.clone();
-            if let Some((x, y)) = map.find_player_start() {
-                self.player.x = x;
-                self.player.y = y;
+            self.player.spawn_at_map(&map, spawn);
+            if map.town {
+                self.player.set_respawn_here();
             }
-            self.combat.spawn_enemies(&map, &self.enemies);
+            self.spawn_map_enemies(&map);
+            self.update_reach_quests();
         }
     }
 
     fn find_npc_at(&self, x: usize, y: usize) -> Option<&Npc> {
-        self.npcs
-            .iter()
-            .find(|npc| npc.map_id == self.player.current_map_id && npc.x == x && npc.y == y)
+        self.npcs.iter().find(|npc| {
+            npc.map_id == self.player.current_map_id
+                && npc.x == x
+                && npc.y == y
+                && self.player.check_conditions(&npc.conditions)
+        })
     }
 
     fn try_interact_with_npc(&mut self) {
@@ -370,18 +748,16 @@ impl RpgGame {
             return;
         };
 
+        self.update_talk_quest(&npc.id);
+
         match npc.npc_type {
             NpcType::Healer => {
+                self.player.set_respawn_here();
                 self.player.stats.current_hp = self.player.stats.max_hp;
                 self.player.stats.current_mp = self.player.stats.max_mp;
 
-                if let Some(dialog) = self.dialogs.iter().find(|d| d.id == npc.dialog_id).cloned() {
-                    let filtered_lines = self.filter_dialog_lines(&dialog);
-                    if !filtered_lines.lines.is_empty() {
-                        self.state =
-                            GameState::Dialog(DialogState::new(npc.name.clone(), &filtered_lines));
-                        return;
-                    }
+                if self.open_dialog(&npc) {
+                    return;
                 }
             }
             NpcType::ShopKeeper => {
@@ -392,115 +768,205 @@ impl RpgGame {
                     .or_else(|| self.shops.first())
                     .cloned();
                 if let Some(shop) = shop {
-                    let shop_items: Vec<_> = shop
-                        .items
-                        .iter()
-                        .filter_map(|item_id| self.items.iter().find(|i| i.id == *item_id).cloned())
-                        .collect();
-                    self.state = GameState::Shop(ShopState::new(shop, shop_items));
+                    self.open_shop(shop);
                     return;
                 }
             }
+            // 여관은 대화의 REST 행동으로 돈을 받고 쉬게 한다
+            NpcType::Inn => self.player.set_respawn_here(),
             NpcType::QuestGiver | NpcType::Villager => {}
         }
 
-        if let Some(dialog) = self.dialogs.iter().find(|d| d.id == npc.dialog_id).cloned() {
-            let filtered_lines = self.filter_dialog_lines(&dialog);
-            if !filtered_lines.lines.is_empty() {
-                self.state = GameState::Dialog(DialogState::new(npc.name.clone(), &filtered_lines));
-            }
-        }
+        self.open_dialog(&npc);
     }
 
-    fn filter_dialog_lines(&self, dialog: &Dialog) -> Dialog {
-        use data::DialogCondition;
-
-        let mut filtered = Vec::new();
-
-        for line in &dialog.lines {
-            let should_show = match &line.condition {
-                None => true,
-                Some(DialogCondition::HasQuest(id)) => self.player.has_quest(id),
-                Some(DialogCondition::QuestComplete(id)) => self.player.is_quest_complete(id),
-                Some(DialogCondition::HasItem(id)) => self.player.has_item(id),
-                Some(DialogCondition::HasGold(amount)) => self.player.stats.gold >= *amount,
-            };
+    fn open_dialog(&mut self, npc: &Npc) -> bool {
+        let state = self
+            .dialogs
+            .iter()
+            .find(|d| d.id == npc.dialog_id)
+            .and_then(|dialog| DialogState::new(npc.name.clone(), dialog, &self.player));
 
-            if should_show {
-                filtered.push(line.clone());
+        match state {
+            Some(state) => {
+                self.state = GameState::Dialog(state);
+                true
             }
+            None => false,
         }
+    }
+
+    /// 현재 줄의 행동을 실행한다. 실행할 수 없어 건너뛰었으면 false
+    fn process_dialog_action(&mut self) -> bool {
+        let GameState::Dialog(ref state) = self.state else {
+            return true;
+        };
+        let actions = state.current_actions().to_vec();
 
-        Dialog {
-            id: dialog.id.clone(),
-            lines: filtered,
+        // 한 줄의 행동은 모두 실행하거나 하나도 실행하지 않는다
+        if !self.can_apply_dialog_actions(&actions) {
+            return false;
         }
+        for action in actions {
+            self.apply_dialog_action(action);
+        }
+        true
     }
 
-    fn process_dialog_action(&mut self) {
-        if let GameState::Dialog(ref state) = self.state
-            && let Some(action) = state.current_action().cloned()
-        {
-            use data::DialogAction;
-            match action {
-                DialogAction::GiveQuest(id) => {
-                    self.player.add_quest(&id);
+    /// 가져가는 행동(아이템, 골드, 수집 퀘스트 납품)을 모두 감당할 수 있는지 확인한다
+    fn can_apply_dialog_actions(&self, actions: &[data::DialogAction]) -> bool {
+        use data::DialogAction;
+
+        let mut items_needed: Vec<(&str, u32)> = Vec::new();
+        let mut gold_needed = 0;
+
+        for action in actions {
+            let (item_id, count) = match action {
+                DialogAction::TakeItem(id, count) => (id.as_str(), *count),
+                DialogAction::TakeGold(amount) => {
+                    gold_needed += amount;
+                    continue;
+                }
+                DialogAction::Rest(price) => {
+                    gold_needed += self.player.rest_price(*price);
+                    continue;
                 }
                 DialogAction::CompleteQuest(id) => {
-                    if let Some(quest) = self.quests.iter().find(|q| q.id == id).cloned() {
-                        self.player.stats.add_exp(quest.reward_exp);
-                        self.player.stats.gold += quest.reward_gold;
-                        if let Some(item_id) = &quest.reward_item
-                            && let Some(item) =
-                                self.items.iter().find(|i| i.id == *item_id).cloned()
-                        {
-                            self.player.add_item(item);
+                    if !self.player.can_turn_in_quest(id) {
+                        return false;
+                    }
+                    match self.quests.iter().find(|q| q.id == *id) {
+                        Some(quest) if quest.quest_type == data::QuestType::Collect => {
+                            (quest.target_id.as_str(), quest.target_count.max(0) as u32)
                         }
-                        self.player.complete_quest(&id);
+                        _ => continue,
                     }
                 }
-                DialogAction::GiveItem(id) => {
-                    if let Some(item) = self.items.iter().find(|i| i.id == id).cloned() {
+                _ => continue,
+            };
+
+            match items_needed.iter_mut().find(|(id, _)| *id == item_id) {
+                Some((_, needed)) => *needed += count,
+                None => items_needed.push((item_id, count)),
+            }
+        }
+
+        self.player.stats.gold >= gold_needed
+            && items_needed
+                .iter()
+                .all(|(id, count)| self.player.count_item(id) >= *count)
+    }
+
+    fn apply_dialog_action(&mut self, action: data::DialogAction) {
+        use data::DialogAction;
+        match action {
+            DialogAction::GiveQuest(id) => {
+                self.player.add_quest(&id);
+                self.update_collect_quests();
+                self.update_reach_quests();
+            }
+            DialogAction::CompleteQuest(id) => {
+                if self.player.can_turn_in_quest(&id)
+                    && let Some(quest) = self.quests.iter().find(|q| q.id == id).cloned()
+                {
+                    // 수집 퀘스트는 모은 아이템을 넘겨준다
+                    if quest.quest_type == data::QuestType::Collect {
+                        self.player
+                            .remove_items(&quest.target_id, quest.target_count.max(0) as u32);
+                    }
+                    self.gain_exp(quest.reward_exp);
+                    self.player.stats.gold += quest.reward_gold;
+                    if let Some(item_id) = &quest.reward_item
+                        && let Some(item) = self.items.iter().find(|i| i.id == *item_id).cloned()
+                    {
                         self.player.add_item(item);
                     }
+                    self.player.complete_quest(&id);
+                    self.update_collect_quests();
                 }
-                DialogAction::TakeItem(id) => {
-                    self.player.remove_item(&id);
-                }
-                DialogAction::GiveGold(amount) => {
-                    self.player.stats.gold += amount;
+            }
+            DialogAction::GiveItem(id, count) => {
+                if let Some(item) = self.items.iter().find(|i| i.id == id).cloned() {
+                    self.player.add_items(item, count);
+                    self.update_collect_quests();
                 }
-                DialogAction::TakeGold(amount) => {
-                    self.player.stats.gold = (self.player.stats.gold - amount).max(0);
-                }
-                DialogAction::OpenShop(id) => {
-                    if let Some(shop) = self.shops.iter().find(|s| s.id == id).cloned() {
-                        let shop_items: Vec<_> = shop
-                            .items
-                            .iter()
-                            .filter_map(|item_id| {
-                                self.items.iter().find(|i| i.id == *item_id).cloned()
-                            })
-                            .collect();
-                        self.state = GameState::Shop(ShopState::new(shop, shop_items));
-                    }
+            }
+            DialogAction::TakeItem(id, count) => {
+                self.player.remove_items(&id, count);
+                self.update_collect_quests();
+            }
+            DialogAction::GiveGold(amount) => {
+                self.player.stats.gold += amount;
+            }
+            DialogAction::TakeGold(amount) => {
+                self.player.stats.gold -= amount;
+            }
+            DialogAction::OpenShop(id) => {
+                if let Some(shop) = self.shops.iter().find(|s| s.id == id).cloned() {
+                    self.open_shop(shop);
                 }
-                DialogAction::Heal => {
-                    self.player.stats.current_hp = self.player.stats.max_hp;
-                    self.player.stats.current_mp = self.player.stats.max_mp;
+            }
+            DialogAction::Heal => {
+                self.player.stats.current_hp = self.player.stats.max_hp;
+                self.player.stats.current_mp = self.player.stats.max_mp;
+            }
+            DialogAction::Rest(price) => {
+                if self.player.pay_for_rest(price) {
+                    self.rest_fade = Some(REST_FADE_TICKS);
                 }
             }
+            DialogAction::SetFlag(name) => {
+                self.player.set_var(&name, 1);
+            }
+            DialogAction::IncVar(name, amount) => {
+                self.player.add_var(&name, amount);
+            }
         }
     }
 
+    /// 페이드가 가장 어두울 때 부른다
+    fn finish_rest(&mut self) {
+        self.player.stats.current_hp = self.player.stats.max_hp;
+        self.player.stats.current_mp = self.player.stats.max_mp;
+        self.player.status.clear();
+
+        // 슬롯이 정해지지 않은 판은 페이드가 끝난 뒤 슬롯을 고르게 한다
+        let text = match self.current_slot.map(|slot| self.save_to_slot(slot)) {
+            Some(true) => "Rested. Game saved",
+            Some(false) => "Rested. Save failed",
+            None => "Rested",
+        };
+        self.notice = Some((String::from(text), NOTICE_TICKS));
+    }
+
     fn handle_inventory_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Up => self.inventory_state.move_up(),
-            KeyCode::Down => self.inventory_state.move_down(self.player.inventory.len()),
-            KeyCode::Ok => {
-                let idx = self.inventory_state.selected;
-                self.player.use_item(idx);
+        let state = &mut self.inventory_state;
+
+        if let Some(selected) = state.menu {
+            let action_count = state.actions(&self.player).len();
+            match key {
+                KeyCode::Up => state.menu = Some(selected.saturating_sub(1)),
+                KeyCode::Down => state.menu = Some((selected + 1).min(action_count - 1)),
+                KeyCode::Ok => {
+                    if let (Some(uid), Some(action)) = (
+                        state.selected_uid(&self.player),
+                        state.selected_action(&self.player),
+                    ) {
+                        self.apply_item_action(uid, action);
+                    }
+                }
+                KeyCode::Back => state.menu = None,
+                _ => {}
             }
+            return;
+        }
+
+        match key {
+            KeyCode::Up => state.move_up(),
+            KeyCode::Down => state.move_down(state.entries(&self.player).len()),
+            KeyCode::Left => state.prev_tab(),
+            KeyCode::Right => state.next_tab(),
+            KeyCode::Ok if !state.actions(&self.player).is_empty() => state.menu = Some(0),
             KeyCode::Back => {
                 self.state = GameState::Explore;
             }
@@ -508,34 +974,164 @@ impl RpgGame {
         }
     }
 
+    fn apply_item_action(&mut self, uid: u32, action: ItemAction) {
+        match action {
+            ItemAction::Use => {
+                self.player.use_item(uid);
+            }
+            ItemAction::Equip => {
+                self.player.equip(uid);
+            }
+            ItemAction::Unequip => self.player.unequip(uid),
+            ItemAction::Drop => {
+                self.player.take_item(uid);
+            }
+        }
+        self.update_collect_quests();
+
+        let state = &mut self.inventory_state;
+        state.menu = None;
+        state.clamp(state.entries(&self.player).len());
+    }
+
     fn handle_stats_input(&mut self, key: KeyCode) {
         if matches!(key, KeyCode::Back | KeyCode::Ok) {
             self.state = GameState::Explore;
         }
     }
 
+    fn save_to_slot(&mut self, slot: usize) -> bool {
+        let map_name = self
+            .current_map()
+            .map(|m| m.name.clone())
+            .unwrap_or_default();
+        self.current_slot = Some(slot);
+        save_game(slot, &self.player, &self.rng, &map_name)
+    }
+
+    /// 저장 슬롯 선택 화면을 연다. 이 판의 슬롯이 없으면 첫 빈 슬롯을 고른 채로 연다
+    fn open_save_picker(&mut self, quit_after_save: bool) {
+        let summaries = read_save_summaries();
+        let selected = self
+            .current_slot
+            .or_else(|| summaries.iter().position(Option::is_none))
+            .unwrap_or(0);
+        let mut state = SlotSelectState::new(SlotMode::Save, summaries, selected);
+        state.quit_after_save = quit_after_save;
+        self.state = GameState::SlotSelect(state);
+    }
+
+    fn return_to_menu(&mut self) {
+        self.state = GameState::Menu(MenuState {
+            selected: 0,
+            has_save: has_save_data(),
+        });
+    }
+
+    fn handle_slot_input(&mut self, key: KeyCode) {
+        let GameState::SlotSelect(ref mut state) = self.state else {
+            return;
+        };
+
+        if state.confirm {
+            match key {
+                KeyCode::Ok => {
+                    let (slot, quit) = (state.selected, state.quit_after_save);
+                    self.finish_save(slot, quit);
+                }
+                KeyCode::Back => state.confirm = false,
+                _ => {}
+            }
+            return;
+        }
+
+        match key {
+            KeyCode::Up => state.move_up(),
+            KeyCode::Down => state.move_down(),
+            KeyCode::Ok => {
+                let (slot, quit) = (state.selected, state.quit_after_save);
+                let occupied = state.selected_summary().is_some();
+                match state.mode {
+                    SlotMode::Load => {
+                        if state
+                            .selected_summary()
+                            .is_some_and(|s| !s.corrupted && !s.ended)
+                        {
+                            self.continue_game(slot);
+                        }
+                    }
+                    SlotMode::Save => {
+                        if occupied {
+                            state.confirm = true;
+                        } else {
+                            self.finish_save(slot, quit);
+                        }
+                    }
+                }
+            }
+            KeyCode::Back => {
+                self.state = match state.mode {
+                    SlotMode::Load => GameState::Menu(MenuState {
+                        selected: 2,
+                        has_save: has_save_data(),
+                    }),
+                    SlotMode::Save => GameState::Explore,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn finish_save(&mut self, slot: usize, quit: bool) {
+        let saved = self.save_to_slot(slot);
+        if quit && saved {
+            self.return_to_menu();
+            return;
+        }
+
+        let text = if saved {
+            format!("Saved to slot {}", slot + 1)
+        } else {
+            String::from("Save failed")
+        };
+        self.notice = Some((text, NOTICE_TICKS));
+        self.state = GameState::Explore;
+    }
+
     fn handle_gameover_input(&mut self, key: KeyCode) {
         if matches!(key, KeyCode::Ok) {
-            self.state = GameState::Menu(MenuState {
-                selected: 0,
-                has_save: has_save_data(),
-            });
+            self.return_to_menu();
         }
     }
 
     fn handle_dialog_input(&mut self, key: KeyCode) {
         match key {
             KeyCode::Ok => {
-                self.process_dialog_action();
+                let applied = self.process_dialog_action();
 
                 if matches!(self.state, GameState::Shop(_)) {
                     return;
                 }
 
-                if let GameState::Dialog(ref mut state) = self.state
-                    && !state.advance()
-                {
-                    self.state = GameState::Explore;
+                if let GameState::Dialog(ref mut state) = self.state {
+                    let more = if applied {
+                        state.advance(&self.player)
+                    } else {
+                        state.advance_failed(&self.player)
+                    };
+                    if !more {
+                        self.state = GameState::Explore;
+                    }
+                }
+            }
+            KeyCode::Up => {
+                if let GameState::Dialog(ref mut state) = self.state {
+                    state.move_choice_up();
+                }
+            }
+            KeyCode::Down => {
+                if let GameState::Dialog(ref mut state) = self.state {
+                    state.move_choice_down();
                 }
             }
             KeyCode::Back => {
@@ -545,9 +1141,76 @@ impl RpgGame {
         }
     }
 
+    fn open_shop(&mut self, shop: Shop) {
+        let shop_items: Vec<_> = shop
+            .items
+            .iter()
+            .filter_map(|(item_id, _)| self.items.iter().find(|i| i.id == *item_id).cloned())
+            .collect();
+        self.state = GameState::Shop(ShopState::new(shop, shop_items));
+    }
+
     fn handle_shop_input(&mut self, key: KeyCode) {
-        if let GameState::Shop(ref mut state) = self.state {
-            match state.mode {
+        let GameState::Shop(ref mut state) = self.state else {
+            return;
+        };
+        state.message = None;
+
+        match state.step {
+            ShopStep::Quantity => match key {
+                KeyCode::Up | KeyCode::Right
+                    if state.quantity < state.max_quantity(&self.player, &self.shop_stock) =>
+                {
+                    state.quantity += 1;
+                }
+                KeyCode::Down | KeyCode::Left if state.quantity > 1 => state.quantity -= 1,
+                KeyCode::Ok => state.step = ShopStep::Confirm,
+                KeyCode::Back => state.step = ShopStep::List,
+                _ => {}
+            },
+            ShopStep::Confirm => match key {
+                KeyCode::Ok => {
+                    state.step = ShopStep::List;
+                    // 확인을 기다리는 동안 바뀐 게 없는지 다시 본다
+                    let count = state
+                        .quantity
+                        .min(state.max_quantity(&self.player, &self.shop_stock));
+                    if count == 0 {
+                        return;
+                    }
+                    match state.mode {
+                        ShopMode::Buy => {
+                            let Some(item) = state.items.get(state.selected).cloned() else {
+                                return;
+                            };
+                            self.player.stats.gold -= state.shop.buy_price(&item) * count as i32;
+                            self.shop_stock.take(&state.shop, &item.id, count);
+                            state.message =
+                                Some((format!("Bought {} x{}", item.name, count), true));
+                            self.player.add_items(item, count);
+                        }
+                        ShopMode::Sell => {
+                            let Some(uid) = self.player.uid_at(state.selected) else {
+                                return;
+                            };
+                            let Some((item, sold)) = self.player.take_items(uid, count) else {
+                                return;
+                            };
+                            self.player.stats.gold += state.shop.sell_price(&item) * sold as i32;
+                            state.message = Some((format!("Sold {} x{}", item.name, sold), true));
+                            if state.selected >= self.player.inventory().len() && state.selected > 0
+                            {
+                                state.selected -= 1;
+                            }
+                        }
+                        ShopMode::Select => return,
+                    }
+                    self.update_collect_quests();
+                }
+                KeyCode::Back => state.step = ShopStep::List,
+                _ => {}
+            },
+            ShopStep::List => match state.mode {
                 ShopMode::Select => match key {
                     KeyCode::Up => state.move_up(),
                     KeyCode::Down => state.move_down(2),
@@ -568,11 +1231,15 @@ impl RpgGame {
                     KeyCode::Up => state.move_up(),
                     KeyCode::Down => state.move_down(state.items.len()),
                     KeyCode::Ok => {
-                        if let Some(item) = state.items.get(state.selected).cloned()
-                            && self.player.stats.gold >= item.price
-                        {
-                            self.player.stats.gold -= item.price;
-                            self.player.add_item(item);
+                        if let Some(item) = state.items.get(state.selected) {
+                            if self.shop_stock.remaining(&state.shop, &item.id) == Some(0) {
+                                state.message = Some((String::from("Sold out"), false));
+                            } else if self.player.stats.gold < state.shop.buy_price(item) {
+                                state.message = Some((String::from("Not enough gold"), false));
+                            } else {
+                                state.step = ShopStep::Quantity;
+                                state.quantity = 1;
+                            }
                         }
                     }
                     KeyCode::Back => {
@@ -583,14 +1250,14 @@ impl RpgGame {
                 },
                 ShopMode::Sell => match key {
                     KeyCode::Up => state.move_up(),
-                    KeyCode::Down => state.move_down(self.player.inventory.len()),
+                    KeyCode::Down => state.move_down(self.player.inventory().len()),
                     KeyCode::Ok => {
-                        if state.selected < self.player.inventory.len() {
-                            let sell_price = self.player.inventory[state.selected].price / 2;
-                            self.player.stats.gold += sell_price;
-                            self.player.inventory.remove(state.selected);
-                            if state.selected >= self.player.inventory.len() && state.selected > 0 {
-                                state.selected -= 1;
+                        if let Some(uid) = self.player.uid_at(state.selected) {
+                            if self.player.is_equipped(uid) {
+                                state.message = Some((String::from("Unequip it first"), false));
+                            } else {
+                                state.step = ShopStep::Quantity;
+                                state.quantity = 1;
                             }
                         }
                     }
@@ -600,6 +1267,19 @@ impl RpgGame {
                     }
                     _ => {}
                 },
+            },
+        }
+    }
+
+    fn handle_content_error_input(&mut self, key: KeyCode) {
+        if let GameState::ContentError(ref mut state) = self.state {
+            match key {
+                KeyCode::Up => state.scroll = state.scroll.saturating_sub(1),
+                KeyCode::Down if state.scroll + 1 < self.content_errors.len() => {
+                    state.scroll += 1;
+                }
+                KeyCode::Back => wipi::kernel::exit(0),
+                _ => {}
             }
         }
     }
@@ -611,33 +1291,73 @@ impl RpgGame {
     }
 
     fn update_kill_quest(&mut self, killed_enemy_id: &str) {
-        for progress in &mut self.player.quests {
-            if progress.completed || progress.rewarded {
-                continue;
-            }
-            if let Some(quest) = self.quests.iter().find(|q| q.id == progress.quest_id)
-                && quest.quest_type == data::QuestType::Kill
-                && quest.target_id == killed_enemy_id
-            {
-                progress.current_count += 1;
-                if progress.current_count >= quest.target_count {
-                    progress.completed = true;
-                }
-            }
-        }
+        game::record_kill(&mut self.player, &self.quests, killed_enemy_id);
+    }
+
+    fn update_talk_quest(&mut self, npc_id: &str) {
+        game::record_talk(&mut self.player, &self.quests, npc_id);
+    }
+
+    fn update_collect_quests(&mut self) {
+        game::update_collect_quests(&mut self.player, &self.quests);
+    }
+
+    fn update_reach_quests(&mut self) {
+        game::update_reach_quests(&mut self.player, &self.quests);
     }
 }
 
 enum MenuAction {
-    NewGame,
+    NewGame { hardcore: bool },
     Continue,
     Exit,
 }
 
 impl App for RpgGame {
     fn on_paint(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+        self.shop_stock.restock(&self.shops, self.ticks);
+        if matches!(
+            self.state,
+            GameState::Explore
+                | GameState::Inventory
+                | GameState::Stats
+                | GameState::Dialog(_)
+                | GameState::Shop(_)
+                | GameState::QuestLog
+        ) {
+            self.player.play_ticks = self.player.play_ticks.saturating_add(1);
+        }
         self.update_combat();
 
+        if let Some((_, ref mut remaining)) = self.notice {
+            *remaining = remaining.saturating_sub(1);
+            if *remaining == 0 {
+                self.notice = None;
+            }
+        }
+        if let Some((_, ref mut remaining)) = self.level_up {
+            *remaining = remaining.saturating_sub(1);
+            if *remaining == 0 {
+                self.level_up = None;
+            }
+        }
+        if let Some(remaining) = self.rest_fade {
+            let remaining = remaining.saturating_sub(1);
+            if remaining == REST_FADE_TICKS / 2 {
+                self.finish_rest();
+            }
+            self.rest_fade = (remaining > 0).then_some(remaining);
+            // 페이드 중에 다른 화면으로 넘어갔거나 쓰러졌다면 저장 슬롯을 묻지 않는다
+            if remaining == 0
+                && self.current_slot.is_none()
+                && matches!(self.state, GameState::Explore | GameState::Dialog(_))
+                && self.player.stats.current_hp > 0
+            {
+                self.open_save_picker(false);
+            }
+        }
+
         let mut fb = Framebuffer::screen_framebuffer();
 
         match &self.state {
@@ -648,6 +1368,12 @@ impl App for RpgGame {
                 if let Some(map) = self.current_map() {
                     draw_explore(&mut fb, map, &self.player, &self.combat, &self.npcs);
                 }
+                if let Some((text, _)) = &self.notice {
+                    draw_notice(&mut fb, text);
+                }
+                if let Some((up, _)) = &self.level_up {
+                    draw_level_up(&mut fb, up);
+                }
             }
             GameState::Inventory => {
                 draw_inventory(&mut fb, &self.player, &self.inventory_state);
@@ -660,9 +1386,15 @@ impl App for RpgGame {
                     draw_explore(&mut fb, map, &self.player, &self.combat, &self.npcs);
                 }
                 draw_dialog(&mut fb, dialog_state);
+                if let Some((text, _)) = &self.notice {
+                    draw_notice(&mut fb, text);
+                }
+                if let Some((up, _)) = &self.level_up {
+                    draw_level_up(&mut fb, up);
+                }
             }
             GameState::Shop(shop_state) => {
-                draw_shop(&mut fb, shop_state, &self.player);
+                draw_shop(&mut fb, shop_state, &self.player, &self.shop_stock);
             }
             GameState::QuestLog => {
                 draw_quest_log(&mut fb, &self.player, &self.quests);
@@ -676,10 +1408,25 @@ impl App for RpgGame {
                 draw_text(&mut fb, w / 2 - 35, h / 2 - 8, "GAME OVER", COLOR_RED);
                 draw_text(&mut fb, w / 2 - 30, h / 2 + 8, "OK:Menu", COLOR_WHITE);
             }
+            GameState::ContentError(state) => {
+                draw_content_errors(&mut fb, &self.content_errors, state);
+            }
+            GameState::SlotSelect(state) => {
+                draw_slot_select(&mut fb, state);
+            }
+        }
+
+        if let Some(remaining) = self.rest_fade {
+            let half = REST_FADE_TICKS / 2;
+            let level = FADE_STEPS - (remaining.abs_diff(half) * FADE_STEPS / half).min(FADE_STEPS);
+            draw_fade(&mut fb, level);
         }
     }
 
     fn on_keydown(&mut self, key: KeyCode) {
+        if self.rest_fade.is_some() {
+            return;
+        }
         match &self.state {
             GameState::Menu(_) => self.handle_menu_input(key),
             GameState::Explore => self.handle_explore_input(key),
@@ -689,6 +1436,8 @@ impl App for RpgGame {
             GameState::Shop(_) => self.handle_shop_input(key),
             GameState::QuestLog => self.handle_quest_input(key),
             GameState::GameOver => self.handle_gameover_input(key),
+            GameState::ContentError(_) => self.handle_content_error_input(key),
+            GameState::SlotSelect(_) => self.handle_slot_input(key),
         }
     }
 }